@@ -0,0 +1,739 @@
+//! Zoom animations between keyframes.
+//!
+//! # Keyframe file format
+//!
+//! A keyframe file is TOML with the frame rate, the settings every frame shares and a list of
+//! keyframes under `keyframe`, in the order they are reached:
+//!
+//! ```toml
+//! frame_rate = 30
+//! motion_blur = 8
+//! shutter_angle = 180
+//! palette_smoothing = 0.5
+//! palette = "frequency 12"
+//! samples = 2
+//!
+//! [[keyframe]]
+//! time = 0
+//! center = [-0.5, 0]
+//!
+//! [[keyframe]]
+//! time = 10
+//! center = [-0.7453, 0.1127]
+//! zoom = 10000
+//! rotation = 0.5
+//! palette_offset = 0.25
+//! iterations = 2000
+//! easing = "ease-in-out"
+//! ```
+//!
+//! `motion_blur` blends that many images spread over `shutter_angle` degrees of the time between
+//! frames into every frame, and `palette_smoothing` averages the iteration limit, which colors are
+//! relative to, over that many seconds around every frame so that they don't flicker.
+//!
+//! `time` is in seconds, `easing` is how the animation eases into a keyframe from the one before,
+//! one of `linear`, `ease-in`, `ease-out` and `ease-in-out`, and `palette_offset` shifts the
+//! palette as in [`Palette::shifted`]. Everything but `time` defaults to the whole set without
+//! easing, and frames between keyframes without `iterations` raise the limit with the zoom level.
+
+use std::{fmt, str::FromStr};
+
+use kurbo::Vec2;
+use serde::Deserialize;
+
+use crate::{
+    antialiasing::Antialiasing,
+    image::Image,
+    palette::{Palette, ParsePaletteError},
+    settings::Settings,
+    view::View,
+    Globals,
+};
+
+/// How the progress between two keyframes speeds up and slows down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    /// Constant speed, which in log-zoom space means the image grows at a constant rate
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map the fraction `t` of the time between two keyframes to the fraction of the way between
+    /// them, both from 0 to 1.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// The state of the animation at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    /// Seconds from the start of the animation
+    pub time: f64,
+    pub view: View,
+    /// How far the palette is shifted, see [`Palette::shifted`]
+    pub palette_offset: f32,
+    /// Iteration limit, `None` to raise it with the zoom level
+    pub max_iterations: Option<u32>,
+    /// How the animation eases into this keyframe from the previous one
+    pub easing: Easing,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, view: View) -> Self {
+        Self {
+            time,
+            view,
+            palette_offset: 0.0,
+            max_iterations: None,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// The state at `time` seconds, between `self` and `next`.
+    ///
+    /// The zoom is interpolated in log space. The center moves as if the view was zoomed about the
+    /// one point that ends up at the same place in the image in both keyframes, which is what
+    /// zooming in on a point with the mouse wheel does, so the target doesn't drift across the
+    /// image on the way in.
+    fn interpolate(&self, next: &Keyframe, time: f64) -> Keyframe {
+        let s = next
+            .easing
+            .apply((time - self.time) / (next.time - self.time));
+        let lerp = |a: f64, b: f64| a + (b - a) * s;
+        let max_iterations = match (self.max_iterations, next.max_iterations) {
+            (Some(a), Some(b)) => Some(lerp((a as f64).ln(), (b as f64).ln()).exp().round() as u32),
+            _ => None,
+        };
+        Keyframe {
+            time,
+            view: self.view.lerp(&next.view, s),
+            palette_offset: lerp(self.palette_offset as f64, next.palette_offset as f64) as f32,
+            max_iterations,
+            easing: next.easing,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AnimationError {
+    Toml(toml::de::Error),
+    Palette(ParsePaletteError),
+    /// An animation needs at least one keyframe and their times may not go backwards
+    Keyframes(String),
+    /// A frame rate, shutter angle or palette smoothing that frames can't be timed with
+    Timing(String),
+}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationError::Toml(err) => write!(f, "invalid keyframe file: {err}"),
+            AnimationError::Palette(err) => err.fmt(f),
+            AnimationError::Keyframes(message) => write!(f, "invalid keyframes: {message}"),
+            AnimationError::Timing(message) => write!(f, "invalid timing: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for AnimationError {}
+
+impl From<toml::de::Error> for AnimationError {
+    fn from(err: toml::de::Error) -> Self {
+        AnimationError::Toml(err)
+    }
+}
+
+impl From<ParsePaletteError> for AnimationError {
+    fn from(err: ParsePaletteError) -> Self {
+        AnimationError::Palette(err)
+    }
+}
+
+/// Keyframes and everything the frames between them share.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    /// Sorted by time, at least one
+    keyframes: Vec<Keyframe>,
+    /// Palette and antialiasing of every frame, the iteration limit comes from the keyframes
+    pub settings: Settings,
+    /// Frames per second
+    pub frame_rate: f64,
+    /// Number of images blended into every frame for motion blur, 1 for none
+    pub motion_blur: u32,
+    /// How much of the time from one frame to the next the images of a frame are spread over, in
+    /// degrees like the shutter of a film camera, so 360 blurs frames into each other
+    pub shutter_angle: f64,
+    /// Seconds over which the iteration limit is averaged. Colors are the iteration count as a
+    /// fraction of the limit, so a limit that jumps between frames makes them flicker.
+    pub palette_smoothing: f64,
+}
+
+impl Animation {
+    pub const DEFAULT_FRAME_RATE: f64 = 30.0;
+    pub const DEFAULT_SHUTTER_ANGLE: f64 = 180.0;
+
+    pub fn new(keyframes: Vec<Keyframe>) -> Result<Self, AnimationError> {
+        if keyframes.is_empty() {
+            return Err(AnimationError::Keyframes("there are none".to_string()));
+        }
+        if let Some(pair) = keyframes.windows(2).find(|it| it[1].time < it[0].time) {
+            return Err(AnimationError::Keyframes(format!(
+                "the keyframe at {} seconds is listed after the one at {}",
+                pair[1].time, pair[0].time
+            )));
+        }
+        Ok(Self {
+            keyframes,
+            settings: Settings::default(),
+            frame_rate: Self::DEFAULT_FRAME_RATE,
+            motion_blur: 1,
+            shutter_angle: Self::DEFAULT_SHUTTER_ANGLE,
+            palette_smoothing: 0.0,
+        })
+    }
+
+    /// Check that frames can be timed with the frame rate, shutter angle and palette smoothing,
+    /// which need to be set by hand.
+    pub fn validate(&self) -> Result<(), AnimationError> {
+        if !(self.frame_rate.is_finite() && self.frame_rate > 0.0) {
+            return Err(AnimationError::Timing(format!(
+                "the frame rate must be positive, not {}",
+                self.frame_rate
+            )));
+        }
+        if !(0.0..=360.0).contains(&self.shutter_angle) {
+            return Err(AnimationError::Timing(format!(
+                "the shutter angle must be from 0 to 360 degrees, not {}",
+                self.shutter_angle
+            )));
+        }
+        if !(self.palette_smoothing.is_finite() && self.palette_smoothing >= 0.0) {
+            return Err(AnimationError::Timing(format!(
+                "the palette smoothing must be 0 or more seconds, not {}",
+                self.palette_smoothing
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Seconds from the first keyframe to the last.
+    pub fn duration(&self) -> f64 {
+        self.keyframes[self.keyframes.len() - 1].time - self.keyframes[0].time
+    }
+
+    /// Number of frames, including one for the first and one for the last keyframe.
+    pub fn frame_count(&self) -> u32 {
+        (self.duration() * self.frame_rate + 1e-9).floor() as u32 + 1
+    }
+
+    /// The state at `time` seconds, holding still before the first and after the last keyframe.
+    pub fn at(&self, time: f64) -> Keyframe {
+        let next = self.keyframes.partition_point(|it| it.time <= time);
+        if next == 0 {
+            return self.keyframes[0];
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1];
+        }
+        self.keyframes[next - 1].interpolate(&self.keyframes[next], time)
+    }
+
+    /// Seconds from the start at which frame `index` is shown.
+    fn frame_time(&self, index: u32) -> f64 {
+        self.keyframes[0].time + index as f64 / self.frame_rate
+    }
+
+    /// The iteration limit at `time` for an image of `width` by `height`, resolving a limit that
+    /// is raised with the zoom level to the one it ends up at.
+    fn iterations_at(&self, time: f64, width: u32, height: u32) -> u32 {
+        let keyframe = self.at(time);
+        keyframe.max_iterations.unwrap_or_else(|| {
+            Globals::for_view(&keyframe.view, &self.settings, width, height).max_iterations()
+        })
+    }
+
+    /// The iteration limit at `time`, averaged over the frames within the palette smoothing
+    /// window around it.
+    fn smoothed_iterations(&self, time: f64, width: u32, height: u32) -> u32 {
+        let reach = (self.palette_smoothing * self.frame_rate / 2.0).round() as i32;
+        let sum: f64 = (-reach..=reach)
+            .map(|frame| {
+                let time = time + frame as f64 / self.frame_rate;
+                self.iterations_at(time, width, height) as f64
+            })
+            .sum();
+        (sum / (2 * reach + 1) as f64).round() as u32
+    }
+
+    /// The state at `time` with the smoothed iteration limit of the frame shown at `frame_time`.
+    fn sample(&self, time: f64, frame_time: f64, width: u32, height: u32) -> (View, Settings) {
+        let keyframe = self.at(time);
+        let settings = Settings {
+            palette: self.settings.palette.shifted(keyframe.palette_offset),
+            max_iterations: Some(self.smoothed_iterations(frame_time, width, height).max(1)),
+            ..self.settings
+        };
+        (keyframe.view, settings)
+    }
+
+    /// The view and settings of frame `index` of a `width` by `height` animation.
+    ///
+    /// The iteration limit is always set. Colors are the iteration count as a fraction of it, so
+    /// the size matters when it is raised with the zoom level.
+    pub fn frame(&self, index: u32, width: u32, height: u32) -> (View, Settings) {
+        self.sample(
+            self.frame_time(index),
+            self.frame_time(index),
+            width,
+            height,
+        )
+    }
+
+    /// The views and settings blended into frame `index`, spread evenly over the time the shutter
+    /// is open which is centered on the frame.
+    ///
+    /// All samples share the iteration limit of the frame so that the palette doesn't smear.
+    pub fn frame_samples(&self, index: u32, width: u32, height: u32) -> Vec<(View, Settings)> {
+        let samples = self.motion_blur.max(1);
+        let time = self.frame_time(index);
+        let open = self.shutter_angle / 360.0 / self.frame_rate;
+        (0..samples)
+            .map(|sample| {
+                let offset = ((sample as f64 + 0.5) / samples as f64 - 0.5) * open;
+                self.sample(time + offset, time, width, height)
+            })
+            .collect()
+    }
+
+    /// Render frame `index`, averaging the images of all of its samples.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_frame(
+        &self,
+        renderer: &crate::headless::Renderer,
+        index: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Image, crate::headless::HeadlessError> {
+        let samples = self.frame_samples(index, width, height);
+        if let [(view, settings)] = samples.as_slice() {
+            return renderer.render(view, settings, width, height);
+        }
+        let mut sum = vec![0u32; width as usize * height as usize * 4];
+        for (view, settings) in &samples {
+            let image = renderer.render(view, settings, width, height)?;
+            for (sum, value) in sum.iter_mut().zip(image.pixels) {
+                *sum += value as u32;
+            }
+        }
+        let count = samples.len() as u32;
+        Ok(Image {
+            width,
+            height,
+            pixels: sum
+                .into_iter()
+                .map(|it| ((it + count / 2) / count) as u8)
+                .collect(),
+        })
+    }
+
+    /// File name of frame `index` in a sequence, numbered so that they sort in order, which
+    /// `ffmpeg` reads with the pattern `frame_%05d.png`.
+    pub fn frame_name(index: u32) -> String {
+        format!("frame_{index:05}.png")
+    }
+
+    /// Render every frame to a numbered PNG in `dir`, see [`Animation::frame_name`], calling
+    /// `on_done` with the report of every frame.
+    ///
+    /// Rendering stops at the first frame that fails, a sequence with a gap is no use.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_frames(
+        &self,
+        renderer: &crate::headless::Renderer,
+        width: u32,
+        height: u32,
+        dir: &std::path::Path,
+        mut on_done: impl FnMut(&crate::batch::JobReport),
+    ) -> Result<(), crate::batch::JobError> {
+        use crate::{batch::JobReport, metadata::Metadata};
+
+        std::fs::create_dir_all(dir)?;
+        for index in 0..self.frame_count() {
+            let output = dir.join(Self::frame_name(index));
+            let start = web_time::Instant::now();
+            let result = (|| {
+                let (view, settings) = self.frame(index, width, height);
+                let metadata = Metadata::new(view, &settings, width, height);
+                self.render_frame(renderer, index, width, height)?
+                    .save_png(&output, Some(&metadata))?;
+                Ok(())
+            })();
+            let failed = result.is_err();
+            let report = JobReport {
+                index: index as usize,
+                output,
+                elapsed: start.elapsed(),
+                result,
+            };
+            on_done(&report);
+            if failed {
+                return report.result;
+            }
+        }
+        Ok(())
+    }
+
+    /// Render every frame into `video`, calling `on_done` with the index of every frame and how
+    /// long it took.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_video<W: std::io::Write>(
+        &self,
+        renderer: &crate::headless::Renderer,
+        video: &mut crate::video::VideoWriter<W>,
+        width: u32,
+        height: u32,
+        mut on_done: impl FnMut(u32, std::time::Duration),
+    ) -> Result<(), crate::batch::JobError> {
+        for index in 0..self.frame_count() {
+            let start = web_time::Instant::now();
+            video.write_frame(&self.render_frame(renderer, index, width, height)?)?;
+            on_done(index, start.elapsed());
+        }
+        Ok(())
+    }
+}
+
+/// A keyframe file, see the [module documentation](self).
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnimationSpec {
+    frame_rate: Option<f64>,
+    motion_blur: Option<u32>,
+    shutter_angle: Option<f64>,
+    palette_smoothing: Option<f64>,
+    palette: Option<String>,
+    samples: Option<u32>,
+    #[serde(rename = "keyframe", default)]
+    keyframes: Vec<KeyframeSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeSpec {
+    time: f64,
+    center: Option<[f64; 2]>,
+    zoom: Option<f64>,
+    rotation: Option<f64>,
+    palette_offset: Option<f32>,
+    iterations: Option<u32>,
+    easing: Option<Easing>,
+}
+
+impl FromStr for Animation {
+    type Err = AnimationError;
+
+    /// Parse a keyframe file.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec: AnimationSpec = toml::from_str(s)?;
+        let keyframes = spec
+            .keyframes
+            .into_iter()
+            .map(|it| {
+                let view = View::default();
+                Keyframe {
+                    palette_offset: it.palette_offset.unwrap_or(0.0),
+                    max_iterations: it.iterations,
+                    easing: it.easing.unwrap_or_default(),
+                    ..Keyframe::new(
+                        it.time,
+                        View {
+                            center: it.center.map_or(view.center, |[x, y]| Vec2::new(x, y)),
+                            zoom: it.zoom.unwrap_or(view.zoom),
+                            rotation: it.rotation.unwrap_or(view.rotation),
+                        },
+                    )
+                }
+            })
+            .collect();
+        let palette = match spec.palette {
+            Some(palette) => palette.parse()?,
+            None => Palette::default(),
+        };
+        let animation = Self {
+            settings: Settings {
+                palette,
+                antialiasing: match spec.samples {
+                    None | Some(0 | 1) => Antialiasing::Off,
+                    Some(n) => Antialiasing::Supersample(n),
+                },
+                ..Settings::default()
+            },
+            frame_rate: spec.frame_rate.unwrap_or(Self::DEFAULT_FRAME_RATE),
+            motion_blur: spec.motion_blur.unwrap_or(1),
+            shutter_angle: spec.shutter_angle.unwrap_or(Self::DEFAULT_SHUTTER_ANGLE),
+            palette_smoothing: spec.palette_smoothing.unwrap_or(0.0),
+            ..Self::new(keyframes)?
+        };
+        animation.validate()?;
+        Ok(animation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zoom_in() -> Animation {
+        Animation::new(vec![
+            Keyframe::new(0.0, View::default()),
+            Keyframe {
+                palette_offset: 1.0,
+                max_iterations: Some(100),
+                ..Keyframe::new(2.0, View::new(Vec2::new(-0.7453, 0.1127), 10000.0))
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_easing_ends() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert!(easing.apply(0.25) < easing.apply(0.75));
+        }
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+    }
+
+    #[test]
+    fn test_frames_hit_keyframes() {
+        let animation = zoom_in();
+        assert_eq!(animation.frame_count(), 61);
+        assert_eq!(
+            animation.frame(0, 640, 480).0,
+            animation.keyframes()[0].view
+        );
+        let (view, settings) = animation.frame(60, 640, 480);
+        assert_eq!(view, animation.keyframes()[1].view);
+        assert_eq!(settings.max_iterations, Some(100));
+        assert_eq!(animation.at(-1.0), animation.keyframes()[0]);
+        assert_eq!(animation.at(3.0), animation.keyframes()[1]);
+    }
+
+    #[test]
+    fn test_zoom_is_constant_speed() {
+        let animation = zoom_in();
+        let zooms: Vec<f64> = (0..=60)
+            .map(|i| animation.frame(i, 640, 480).0.zoom)
+            .collect();
+        let rate = zooms[1] / zooms[0];
+        for pair in zooms.windows(2) {
+            assert!((pair[1] / pair[0] - rate).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_center_zooms_about_fixed_point() {
+        let animation = zoom_in();
+        let viewport = Vec2::new(640.0, 480.0);
+        let [a, b] = [0, 60].map(|i| animation.frame(i, 640, 480).0.pixel_to_complex(viewport));
+        // the pixel that shows the same point at the start and the end
+        let fixed = {
+            let [m, _, _, d, e, f] = (a.inverse() * b).as_coeffs();
+            kurbo::Point::new(e / (1.0 - m), f / (1.0 - d))
+        };
+        let point = a * fixed;
+        for i in [10, 30, 45] {
+            let frame = animation.frame(i, 640, 480).0.pixel_to_complex(viewport);
+            assert!((frame * fixed - point).hypot() < 1e-9, "frame {i}");
+        }
+    }
+
+    #[test]
+    fn test_motion_blur_spans_shutter() {
+        let animation = Animation {
+            motion_blur: 4,
+            shutter_angle: 360.0,
+            ..zoom_in()
+        };
+        let samples = animation.frame_samples(30, 640, 480);
+        assert_eq!(samples.len(), 4);
+        // the samples cover the time from half a frame before to half a frame after
+        let [before, after] = [29, 31].map(|i| animation.frame(i, 640, 480).0.zoom);
+        let frame = animation.frame(30, 640, 480);
+        assert!(before < samples[0].0.zoom && samples[3].0.zoom < after);
+        assert!(samples[1].0.zoom < frame.0.zoom && frame.0.zoom < samples[2].0.zoom);
+        assert!(samples
+            .iter()
+            .all(|(_, settings)| settings.max_iterations == frame.1.max_iterations));
+
+        let still = Animation {
+            shutter_angle: 0.0,
+            ..animation
+        };
+        assert!(still
+            .frame_samples(30, 640, 480)
+            .iter()
+            .all(|it| *it == still.frame(30, 640, 480)));
+    }
+
+    #[test]
+    fn test_palette_smoothing() {
+        // the limit jumps from 100 to 400 after one second
+        let keyframes = [(0.0, 100), (1.0, 100), (1.0, 400), (2.0, 400)]
+            .map(|(time, limit)| Keyframe {
+                max_iterations: Some(limit),
+                ..Keyframe::new(time, View::default())
+            })
+            .to_vec();
+        let iterations = |animation: &Animation, index| {
+            animation.frame(index, 640, 480).1.max_iterations.unwrap()
+        };
+        let sharp = Animation::new(keyframes).unwrap();
+        let smooth = Animation {
+            palette_smoothing: 1.0,
+            ..sharp.clone()
+        };
+        assert_eq!(iterations(&sharp, 30), 400);
+        assert!(iterations(&smooth, 30) < 300);
+        assert_eq!(iterations(&smooth, 60), 400);
+        // the largest change from one frame to the next is much smaller
+        let largest_step = |animation: &Animation| {
+            (1..=60)
+                .map(|i| iterations(animation, i).abs_diff(iterations(animation, i - 1)))
+                .max()
+                .unwrap()
+        };
+        assert!(largest_step(&smooth) * 2 < largest_step(&sharp));
+    }
+
+    #[test]
+    fn test_parse_keyframe_file() {
+        let animation: Animation = r#"
+            frame_rate = 24
+            samples = 2
+
+            [[keyframe]]
+            time = 0
+
+            [[keyframe]]
+            time = 2
+            center = [-0.7453, 0.1127]
+            zoom = 10000
+            palette_offset = 1
+            iterations = 100
+            easing = "ease-in-out"
+            "#
+        .parse()
+        .unwrap();
+        assert_eq!(animation.frame_rate, 24.0);
+        assert_eq!(
+            animation.settings.antialiasing,
+            Antialiasing::Supersample(2)
+        );
+        assert_eq!(
+            animation.keyframes()[1],
+            Keyframe {
+                easing: Easing::EaseInOut,
+                ..zoom_in().keyframes()[1]
+            }
+        );
+        assert!("[[keyframe]]\ntime = 1\n[[keyframe]]\ntime = 0"
+            .parse::<Animation>()
+            .is_err());
+        assert!("frame_rate = 30".parse::<Animation>().is_err());
+        for timing in [
+            "frame_rate = 0",
+            "frame_rate = -24",
+            "frame_rate = nan",
+            "shutter_angle = 400",
+            "shutter_angle = -1",
+            "palette_smoothing = -0.5",
+            "palette_smoothing = inf",
+        ] {
+            let file = format!("{timing}\n[[keyframe]]\ntime = 0");
+            assert!(
+                matches!(file.parse::<Animation>(), Err(AnimationError::Timing(_))),
+                "{timing}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_frames() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-frames-{}", std::process::id()));
+        let animation = Animation {
+            frame_rate: 2.0,
+            ..zoom_in()
+        };
+        let mut done = Vec::new();
+        animation
+            .render_frames(&crate::headless::Renderer::Cpu, 16, 12, &dir, |report| {
+                done.push(report.output.clone())
+            })
+            .unwrap();
+        assert_eq!(
+            done,
+            (0..5)
+                .map(|i| dir.join(Animation::frame_name(i)))
+                .collect::<Vec<_>>()
+        );
+        assert!(done.iter().all(|it| it.exists()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_video() {
+        use crate::video::{VideoFormat, VideoWriter};
+
+        let animation = Animation {
+            frame_rate: 2.0,
+            ..zoom_in()
+        };
+        let mut video = VideoWriter::new(Vec::new(), VideoFormat::Rgba, 16, 12, 2.0).unwrap();
+        let mut done = Vec::new();
+        animation
+            .render_video(
+                &crate::headless::Renderer::Cpu,
+                &mut video,
+                16,
+                12,
+                |index, _| done.push(index),
+            )
+            .unwrap();
+        assert_eq!(done, [0, 1, 2, 3, 4]);
+        assert_eq!(video.finish().unwrap().len(), 5 * 16 * 12 * 4);
+
+        let blurred = Animation {
+            motion_blur: 3,
+            ..animation
+        };
+        let image = blurred
+            .render_frame(&crate::headless::Renderer::Cpu, 2, 16, 12)
+            .unwrap();
+        assert_eq!(image.pixels.len(), 16 * 12 * 4);
+        assert!(image.pixels.chunks_exact(4).all(|pixel| pixel[3] == 255));
+    }
+}
@@ -0,0 +1,84 @@
+/// How the edges of the set get smoothed out.
+///
+/// All of these only kick in once the view has been refined to full resolution, so coarse passes
+/// stay as fast as without antialiasing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Antialiasing {
+    #[default]
+    Off,
+    /// Average a fixed grid of `n` by `n` samples in every pixel.
+    Supersample(u32),
+    /// Keep blending in frames with the sample moved around within each pixel, `n` frames in total.
+    Jitter(u32),
+    /// Take a grid of `n` by `n` samples, but only in pixels whose neighbors differ strongly in
+    /// iteration count. Everywhere else one sample is plenty.
+    Adaptive(u32),
+}
+
+/// The smallest difference in iteration count, as a fraction of the iteration budget, between a
+/// pixel and one of its neighbors that makes adaptive antialiasing resample the pixel.
+pub const EDGE_THRESHOLD: f32 = 0.02;
+
+impl Antialiasing {
+    /// The modes cycled through when toggling antialiasing in the window.
+    pub const PRESETS: [Antialiasing; 5] = [
+        Antialiasing::Off,
+        Antialiasing::Supersample(2),
+        Antialiasing::Supersample(4),
+        Antialiasing::Jitter(16),
+        Antialiasing::Adaptive(4),
+    ];
+
+    /// The preset that comes after this one, wrapping around to the first.
+    pub fn next(self) -> Self {
+        let index = Self::PRESETS
+            .iter()
+            .position(|it| *it == self)
+            .map_or(0, |index| index + 1);
+        Self::PRESETS[index % Self::PRESETS.len()]
+    }
+
+    /// The number of samples along each axis of a pixel in the full resolution pass.
+    pub fn samples(self) -> u32 {
+        match self {
+            Antialiasing::Supersample(n) => n.max(1),
+            _ => 1,
+        }
+    }
+}
+
+/// Offset from the pixel center of jittered sample `index`, in pixels.
+///
+/// The offsets follow the 2, 3 Halton sequence which spreads any number of consecutive samples
+/// evenly over the pixel. Sample 0 is the pixel center itself.
+pub fn jitter(index: u32) -> [f32; 2] {
+    fn halton(mut index: u32, base: u32) -> f32 {
+        let mut fraction = 1.0;
+        let mut result = 0.0;
+        while index > 0 {
+            fraction /= base as f32;
+            result += fraction * (index % base) as f32;
+            index /= base;
+        }
+        result
+    }
+    if index == 0 {
+        return [0.0, 0.0];
+    }
+    [halton(index, 2) - 0.5, halton(index, 3) - 0.5]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_in_pixel() {
+        assert_eq!(jitter(0), [0.0, 0.0]);
+        assert_eq!(jitter(1), [0.0, 1.0 / 3.0 - 0.5]);
+        for index in 0..64 {
+            let [x, y] = jitter(index);
+            assert!((-0.5..0.5).contains(&x) && (-0.5..0.5).contains(&y));
+        }
+    }
+}
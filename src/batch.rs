@@ -0,0 +1,472 @@
+//! Rendering many images in one process from a job file.
+//!
+//! # Job file format
+//!
+//! A job file is TOML, or JSON when its name ends in `.json`, with a list of jobs under `job`.
+//! Only `output` is required, everything else defaults to the whole set at 1920 by 1080:
+//!
+//! ```toml
+//! [[job]]
+//! output = "thumbnails/seahorse.png"
+//! center = [-0.7453, 0.1127]
+//! zoom = 100
+//! rotation = 0.0
+//! width = 256
+//! height = 256
+//! palette = "phase 0 0.1 0.2"
+//! iterations = 500
+//! samples = 2
+//! formula = "z^2 + c"
+//! depth = 8
+//! ```
+//!
+//! `samples` supersamples every pixel `samples` by `samples` times and `depth` is 8 or 16 bits per
+//! channel of a PNG. Like with the `render` command the extension of `output` picks the format:
+//! `.png`, `.exr` or `.raw` for [raw per-pixel data](crate::data). Only 8 bit PNGs can be
+//! supersampled, the other formats hold a single sample per pixel. Relative output paths are
+//! relative to the job file.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use kurbo::Vec2;
+use serde::Deserialize;
+
+use crate::{
+    antialiasing::Antialiasing,
+    cpu,
+    headless::{HeadlessError, Renderer},
+    metadata::{Metadata, FORMULA},
+    palette::Palette,
+    poster::{Poster, PosterError},
+    settings::Settings,
+    view::View,
+};
+
+#[derive(Debug)]
+pub enum BatchError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::Io(err) => write!(f, "failed to read the job file: {err}"),
+            BatchError::Toml(err) => write!(f, "invalid job file: {err}"),
+            BatchError::Json(err) => write!(f, "invalid job file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<io::Error> for BatchError {
+    fn from(err: io::Error) -> Self {
+        BatchError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for BatchError {
+    fn from(err: toml::de::Error) -> Self {
+        BatchError::Toml(err)
+    }
+}
+
+impl From<serde_json::Error> for BatchError {
+    fn from(err: serde_json::Error) -> Self {
+        BatchError::Json(err)
+    }
+}
+
+/// Why a single job failed, the other jobs of a batch go on regardless.
+#[derive(Debug)]
+pub enum JobError {
+    Render(HeadlessError),
+    Poster(PosterError),
+    Png(png::EncodingError),
+    Exr(exr::error::Error),
+    Io(io::Error),
+    /// The output isn't a `.png`, `.exr` or `.raw` file
+    Format(PathBuf),
+    /// PNGs are only written with 8 or 16 bits per channel
+    Depth(u8),
+    /// Only 8 bit PNGs are supersampled, the other formats store one sample per pixel
+    Supersampling(PathBuf),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Render(err) => write!(f, "failed to render: {err}"),
+            JobError::Poster(err) => err.fmt(f),
+            JobError::Png(err) => write!(f, "failed to write the image: {err}"),
+            JobError::Exr(err) => write!(f, "failed to write the image: {err}"),
+            JobError::Io(err) => write!(f, "failed to write the image: {err}"),
+            JobError::Format(path) => write!(
+                f,
+                "don't know how to write {}, use .png, .exr or .raw",
+                path.display()
+            ),
+            JobError::Depth(depth) => write!(f, "can't write {depth} bit PNGs, only 8 or 16"),
+            JobError::Supersampling(path) => write!(
+                f,
+                "can't supersample {}, only 8 bit PNGs can be",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+impl From<HeadlessError> for JobError {
+    fn from(err: HeadlessError) -> Self {
+        JobError::Render(err)
+    }
+}
+
+impl From<PosterError> for JobError {
+    fn from(err: PosterError) -> Self {
+        JobError::Poster(err)
+    }
+}
+
+impl From<png::EncodingError> for JobError {
+    fn from(err: png::EncodingError) -> Self {
+        JobError::Png(err)
+    }
+}
+
+impl From<exr::error::Error> for JobError {
+    fn from(err: exr::error::Error) -> Self {
+        JobError::Exr(err)
+    }
+}
+
+impl From<io::Error> for JobError {
+    fn from(err: io::Error) -> Self {
+        JobError::Io(err)
+    }
+}
+
+/// One image to render.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "JobSpec")]
+pub struct Job {
+    pub view: View,
+    pub settings: Settings,
+    pub width: u32,
+    pub height: u32,
+    /// Bits per channel of a PNG, PNGs with 16 are rendered on the CPU
+    pub depth: u8,
+    pub output: PathBuf,
+}
+
+/// A job as it is written in a job file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobSpec {
+    output: PathBuf,
+    center: Option<[f64; 2]>,
+    zoom: Option<f64>,
+    rotation: Option<f64>,
+    width: Option<u32>,
+    height: Option<u32>,
+    palette: Option<String>,
+    iterations: Option<u32>,
+    samples: Option<u32>,
+    formula: Option<String>,
+    depth: Option<u8>,
+}
+
+impl TryFrom<JobSpec> for Job {
+    type Error = String;
+
+    fn try_from(spec: JobSpec) -> Result<Self, String> {
+        if let Some(formula) = spec.formula.filter(|it| it != FORMULA) {
+            return Err(format!(
+                "unsupported formula `{formula}`, only `{FORMULA}` is"
+            ));
+        }
+        let palette = match spec.palette {
+            Some(palette) => palette.parse::<Palette>().map_err(|err| err.to_string())?,
+            None => Palette::default(),
+        };
+        let view = View::default();
+        let job = Job::new(
+            View {
+                center: spec.center.map_or(view.center, |[x, y]| Vec2::new(x, y)),
+                zoom: spec.zoom.unwrap_or(view.zoom),
+                rotation: spec.rotation.unwrap_or(view.rotation),
+            },
+            Settings {
+                palette,
+                max_iterations: spec.iterations,
+                antialiasing: match spec.samples {
+                    None | Some(0 | 1) => Antialiasing::Off,
+                    Some(n) => Antialiasing::Supersample(n),
+                },
+            },
+            spec.output,
+        );
+        Ok(Job {
+            width: spec.width.unwrap_or(job.width),
+            height: spec.height.unwrap_or(job.height),
+            depth: spec.depth.unwrap_or(job.depth),
+            ..job
+        })
+    }
+}
+
+impl Job {
+    /// A 1920 by 1080 job with 8 bits per channel.
+    pub fn new(view: View, settings: Settings, output: impl Into<PathBuf>) -> Self {
+        Self {
+            view,
+            settings,
+            width: 1920,
+            height: 1080,
+            depth: 8,
+            output: output.into(),
+        }
+    }
+
+    /// Render the image and write it to [`Job::output`], creating missing directories.
+    ///
+    /// 8 bit PNGs larger than `renderer` can render at once are rendered as a [`Poster`], every
+    /// other format is rendered on the CPU which is the only renderer that produces per-pixel data.
+    pub fn render(&self, renderer: &Renderer) -> Result<(), JobError> {
+        let Job {
+            view,
+            settings,
+            width,
+            height,
+            depth,
+            ref output,
+        } = *self;
+        let extension = output
+            .extension()
+            .and_then(|it| it.to_str())
+            .map(str::to_ascii_lowercase);
+        if !matches!(extension.as_deref(), Some("png" | "exr" | "raw")) {
+            return Err(JobError::Format(output.clone()));
+        }
+        let supersampled = matches!(settings.antialiasing, Antialiasing::Supersample(n) if n > 1);
+        if supersampled && !(extension.as_deref() == Some("png") && depth == 8) {
+            return Err(JobError::Supersampling(output.clone()));
+        }
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let metadata = Metadata::new(view, &settings, width, height);
+        match extension.as_deref() {
+            Some("png") if depth == 8 => {
+                if width.max(height) > renderer.max_size() {
+                    Poster {
+                        settings,
+                        ..Poster::new(view, width, height)
+                    }
+                    .save_png(renderer, output)?;
+                } else {
+                    renderer
+                        .render(&view, &settings, width, height)?
+                        .save_png(output, Some(&metadata))?;
+                }
+            }
+            Some("png") if depth == 16 => cpu::render_data(&view, &settings, width, height)
+                .save_png16(&settings.palette, output, Some(&metadata))?,
+            Some("png") => return Err(JobError::Depth(depth)),
+            Some("exr") => cpu::render_data(&view, &settings, width, height)
+                .save_exr(&settings.palette, output)?,
+            _ => cpu::render_data(&view, &settings, width, height).save_raw(output)?,
+        }
+        Ok(())
+    }
+}
+
+/// How a job of a batch went.
+#[derive(Debug)]
+pub struct JobReport {
+    /// Position of the job in the batch
+    pub index: usize,
+    pub output: PathBuf,
+    pub elapsed: Duration,
+    pub result: Result<(), JobError>,
+}
+
+/// The jobs of a job file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Batch {
+    #[serde(rename = "job", default)]
+    pub jobs: Vec<Job>,
+}
+
+impl Batch {
+    pub fn from_toml(text: &str) -> Result<Self, BatchError> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, BatchError> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    /// Read a job file, JSON if its extension is `.json` and TOML otherwise, and make the output
+    /// paths relative to the directory it is in.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BatchError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mut batch = if path.extension().is_some_and(|it| it == "json") {
+            Self::from_json(&text)?
+        } else {
+            Self::from_toml(&text)?
+        };
+        if let Some(dir) = path.parent() {
+            for job in &mut batch.jobs {
+                job.output = dir.join(&job.output);
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Render every job with the same `renderer`, calling `on_done` after each one. A failed job
+    /// doesn't stop the batch.
+    pub fn run(&self, renderer: &Renderer, mut on_done: impl FnMut(&JobReport)) -> Vec<JobReport> {
+        self.jobs
+            .iter()
+            .enumerate()
+            .map(|(index, job)| {
+                let start = Instant::now();
+                let result = job.render(renderer);
+                let report = JobReport {
+                    index,
+                    output: job.output.clone(),
+                    elapsed: start.elapsed(),
+                    result,
+                };
+                on_done(&report);
+                report
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::PixelData, image::Image};
+
+    #[test]
+    fn test_parse_job_file() {
+        let toml = Batch::from_toml(
+            r#"
+            [[job]]
+            output = "a.png"
+
+            [[job]]
+            output = "b.png"
+            center = [-0.75, 0.1]
+            zoom = 30
+            width = 64
+            height = 48
+            palette = "frequency 3"
+            iterations = 200
+            samples = 2
+            formula = "z^2 + c"
+            "#,
+        )
+        .unwrap();
+        let json = Batch::from_json(
+            r#"{"job": [
+                {"output": "a.png"},
+                {"output": "b.png", "center": [-0.75, 0.1], "zoom": 30, "width": 64, "height": 48,
+                 "palette": "frequency 3", "iterations": 200, "samples": 2}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(toml, json);
+        assert_eq!(
+            toml.jobs[0],
+            Job::new(View::default(), Settings::default(), "a.png")
+        );
+        assert_eq!(
+            toml.jobs[1],
+            Job {
+                width: 64,
+                height: 48,
+                ..Job::new(
+                    View::new(Vec2::new(-0.75, 0.1), 30.0),
+                    Settings {
+                        palette: "frequency 3".parse().unwrap(),
+                        max_iterations: Some(200),
+                        antialiasing: Antialiasing::Supersample(2),
+                    },
+                    "b.png"
+                )
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_job_file() {
+        for toml in [
+            "[[job]]\nzoom = 2",
+            "[[job]]\noutput = \"a.png\"\nformula = \"z^3 + c\"",
+            "[[job]]\noutput = \"a.png\"\npalette = \"hue 3\"",
+            "[[job]]\noutput = \"a.png\"\ncolour = 1",
+        ] {
+            assert!(Batch::from_toml(toml).is_err(), "{toml}");
+        }
+    }
+
+    #[test]
+    fn test_run_reports_every_job() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-batch-{}", std::process::id()));
+        let job = |output: &str| Job {
+            width: 16,
+            height: 12,
+            ..Job::new(View::default(), Settings::default(), dir.join(output))
+        };
+        let batch = Batch {
+            jobs: vec![
+                job("nested/a.png"),
+                job("b.bmp"),
+                Job {
+                    depth: 16,
+                    ..job("c.png")
+                },
+                job("d.raw"),
+                Job {
+                    settings: Settings {
+                        antialiasing: Antialiasing::Supersample(2),
+                        ..Settings::default()
+                    },
+                    ..job("e.exr")
+                },
+            ],
+        };
+        let mut done = Vec::new();
+        let reports = batch.run(&Renderer::Cpu, |report| done.push(report.index));
+        assert_eq!(done, [0, 1, 2, 3, 4]);
+        assert!(reports[0].result.is_ok());
+        assert!(matches!(reports[1].result, Err(JobError::Format(_))));
+        assert!(reports[2].result.is_ok());
+        assert!(reports[3].result.is_ok());
+        assert!(matches!(reports[4].result, Err(JobError::Supersampling(_))));
+        assert!(!dir.join("e.exr").exists());
+
+        let image = Image::load_png(dir.join("nested/a.png")).unwrap();
+        assert_eq!((image.width, image.height), (16, 12));
+        assert_eq!(
+            Metadata::load_png(dir.join("c.png")).unwrap().view,
+            View::default()
+        );
+        assert_eq!(PixelData::load_raw(dir.join("d.raw")).unwrap().width, 16);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
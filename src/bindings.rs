@@ -0,0 +1,710 @@
+//! What keys and mouse buttons do in the window.
+//!
+//! # Bindings file format
+//!
+//! Bindings are read from TOML in the user's config directory (see [`Bindings::default_path`]).
+//! Everything is optional, the file only lists what differs from the defaults:
+//!
+//! ```toml
+//! # fraction of the window a pan moves by
+//! pan_step = 0.1
+//! # zoom factor of a zoom step
+//! zoom_step = 1.5
+//! # zoom factor of a fine zoom step
+//! fine_zoom_step = 1.1
+//! # degrees a rotation step turns by
+//! rotation_step = 36
+//! # zoom factor of a line of mouse wheel scrolling
+//! wheel_zoom = 1.05
+//! # degrees the rotation snaps to while shift is held, 0 to never snap
+//! snap_angle = 15
+//! # what ctrl dragging turns the view about, "center" or "cursor"
+//! rotation_pivot = "center"
+//! # start from no bindings at all instead of the defaults
+//! replace_defaults = false
+//!
+//! [bindings]
+//! "w" = "pan-up"
+//! "ctrl+shift+z" = "redo"
+//! "mouse-middle" = "reset"
+//! "space" = "none"
+//! ```
+//!
+//! A binding is any number of the modifiers `ctrl`, `shift`, `alt` and `super`, followed by a
+//! key or mouse button and joined with `+`. Keys are the character they type, or one of `space`,
+//! `tab`, `enter`, `escape`, `backspace`, `delete`, `insert`, `home`, `end`, `pageup`, `pagedown`,
+//! `left`, `right`, `up`, `down` and `f1` to `f12`, and `plus` and `minus` for those characters.
+//! Mouse buttons are `mouse-left`, `mouse-right`, `mouse-middle`, `mouse-back`, `mouse-forward`
+//! and `mouse-N` for any other one. Left and right keep dragging the view, turning it with ctrl
+//! and zoom rectangles when they are bound. The actions are the names of [`Action`], with `none`
+//! to unbind.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    f64::consts::TAU,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+use crate::rotation::Pivot;
+use winit::{
+    event::MouseButton,
+    keyboard::{Key, ModifiersState, NamedKey, SmolStr},
+};
+
+/// Something the window can be asked to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    /// Move the view left, which slides the image right
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    /// Zoom in by a step, about the cursor
+    ZoomIn,
+    ZoomOut,
+    /// Zoom in by a fine step, about the cursor
+    ZoomInFine,
+    ZoomOutFine,
+    /// Turn the image clockwise by a step, about the cursor
+    RotateCw,
+    RotateCcw,
+    /// Round the rotation to the nearest multiple of the snap angle, straightening the view
+    SnapRotation,
+    /// Go back to the whole set
+    Reset,
+    Undo,
+    Redo,
+    /// Save the image shown in the window as a PNG
+    Screenshot,
+    CycleAntialiasing,
+    /// Save the view as a new bookmark
+    Bookmark,
+    NextBookmark,
+    PreviousBookmark,
+    /// Jump to the bookmark with this index, counting from 0
+    JumpToBookmark(usize),
+    /// Show or hide the coordinates, zoom and frame rate over the image
+    ToggleHud,
+    /// Stop dragging out a zoom rectangle
+    Cancel,
+}
+
+impl Action {
+    const NAMES: [(&'static str, Action); 21] = [
+        ("pan-left", Action::PanLeft),
+        ("pan-right", Action::PanRight),
+        ("pan-up", Action::PanUp),
+        ("pan-down", Action::PanDown),
+        ("zoom-in", Action::ZoomIn),
+        ("zoom-out", Action::ZoomOut),
+        ("zoom-in-fine", Action::ZoomInFine),
+        ("zoom-out-fine", Action::ZoomOutFine),
+        ("rotate-cw", Action::RotateCw),
+        ("rotate-ccw", Action::RotateCcw),
+        ("snap-rotation", Action::SnapRotation),
+        ("reset", Action::Reset),
+        ("undo", Action::Undo),
+        ("redo", Action::Redo),
+        ("screenshot", Action::Screenshot),
+        ("cycle-antialiasing", Action::CycleAntialiasing),
+        ("bookmark", Action::Bookmark),
+        ("next-bookmark", Action::NextBookmark),
+        ("previous-bookmark", Action::PreviousBookmark),
+        ("toggle-hud", Action::ToggleHud),
+        ("cancel", Action::Cancel),
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Action::JumpToBookmark(index) = self {
+            return write!(f, "jump-to-bookmark-{}", index + 1);
+        }
+        let (name, _) = Self::NAMES
+            .iter()
+            .find(|(_, action)| action == self)
+            .unwrap();
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    /// Parse an action from its kebab case name, with bookmarks counted from 1 as in
+    /// `jump-to-bookmark-1`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        if let Some(number) = s.strip_prefix("jump-to-bookmark-") {
+            return match number.parse::<usize>() {
+                Ok(number) if number >= 1 => Ok(Action::JumpToBookmark(number - 1)),
+                _ => Err(format!("`{s}` needs a bookmark number from 1")),
+            };
+        }
+        Self::NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(_, action)| action)
+            .ok_or_else(|| format!("unknown action `{s}`"))
+    }
+}
+
+/// A key or mouse button.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Input {
+    /// A named key or the lowercase character a key types
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+/// An input together with the modifiers held with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Chord {
+    pub modifiers: ModifiersState,
+    pub input: Input,
+}
+
+impl Chord {
+    pub fn new(modifiers: ModifiersState, input: Input) -> Self {
+        let input = match input {
+            Input::Key(Key::Character(c)) => Input::Key(Key::Character(c.to_lowercase().into())),
+            input => input,
+        };
+        Self { modifiers, input }
+    }
+
+    pub fn key(modifiers: ModifiersState, key: Key) -> Self {
+        Self::new(modifiers, Input::Key(key))
+    }
+
+    pub fn mouse(modifiers: ModifiersState, button: MouseButton) -> Self {
+        Self::new(modifiers, Input::Mouse(button))
+    }
+}
+
+const MODIFIER_NAMES: [(&str, ModifiersState); 4] = [
+    ("ctrl", ModifiersState::CONTROL),
+    ("shift", ModifiersState::SHIFT),
+    ("alt", ModifiersState::ALT),
+    ("super", ModifiersState::SUPER),
+];
+
+const KEY_NAMES: [(&str, NamedKey); 15] = [
+    ("space", NamedKey::Space),
+    ("tab", NamedKey::Tab),
+    ("enter", NamedKey::Enter),
+    ("escape", NamedKey::Escape),
+    ("backspace", NamedKey::Backspace),
+    ("delete", NamedKey::Delete),
+    ("insert", NamedKey::Insert),
+    ("home", NamedKey::Home),
+    ("end", NamedKey::End),
+    ("pageup", NamedKey::PageUp),
+    ("pagedown", NamedKey::PageDown),
+    ("left", NamedKey::ArrowLeft),
+    ("right", NamedKey::ArrowRight),
+    ("up", NamedKey::ArrowUp),
+    ("down", NamedKey::ArrowDown),
+];
+
+const FUNCTION_KEYS: [NamedKey; 12] = [
+    NamedKey::F1,
+    NamedKey::F2,
+    NamedKey::F3,
+    NamedKey::F4,
+    NamedKey::F5,
+    NamedKey::F6,
+    NamedKey::F7,
+    NamedKey::F8,
+    NamedKey::F9,
+    NamedKey::F10,
+    NamedKey::F11,
+    NamedKey::F12,
+];
+
+const MOUSE_NAMES: [(&str, MouseButton); 5] = [
+    ("mouse-left", MouseButton::Left),
+    ("mouse-right", MouseButton::Right),
+    ("mouse-middle", MouseButton::Middle),
+    ("mouse-back", MouseButton::Back),
+    ("mouse-forward", MouseButton::Forward),
+];
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, modifier) in MODIFIER_NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match &self.input {
+            Input::Key(Key::Character(c)) if c == "+" => f.write_str("plus"),
+            Input::Key(Key::Character(c)) if c == "-" => f.write_str("minus"),
+            Input::Key(Key::Character(c)) => f.write_str(c),
+            Input::Key(Key::Named(named)) => {
+                if let Some((name, _)) = KEY_NAMES.iter().find(|(_, key)| key == named) {
+                    f.write_str(name)
+                } else if let Some(n) = FUNCTION_KEYS.iter().position(|key| key == named) {
+                    write!(f, "f{}", n + 1)
+                } else {
+                    write!(f, "{named:?}")
+                }
+            }
+            Input::Key(key) => write!(f, "{key:?}"),
+            Input::Mouse(MouseButton::Other(n)) => write!(f, "mouse-{n}"),
+            Input::Mouse(button) => {
+                let (name, _) = MOUSE_NAMES.iter().find(|(_, it)| it == button).unwrap();
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let lowercase = s.to_lowercase();
+        let mut parts = lowercase.split('+').collect::<Vec<_>>();
+        // a trailing + is the key itself, as in `ctrl++`
+        if parts.len() > 1 && parts[parts.len() - 1].is_empty() && parts[parts.len() - 2].is_empty()
+        {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let (input, modifier_names) = parts.split_last().ok_or("empty binding")?;
+        let mut modifiers = ModifiersState::empty();
+        for name in modifier_names {
+            let (_, modifier) = MODIFIER_NAMES
+                .iter()
+                .find(|(it, _)| it == name)
+                .ok_or_else(|| format!("unknown modifier `{name}` in `{s}`"))?;
+            modifiers |= *modifier;
+        }
+
+        let function_key = input
+            .strip_prefix('f')
+            .and_then(|n| n.parse::<usize>().ok())
+            .and_then(|n| FUNCTION_KEYS.get(n.wrapping_sub(1)));
+        let mouse_button = input
+            .strip_prefix("mouse-")
+            .and_then(|n| n.parse::<u16>().ok())
+            .map(MouseButton::Other);
+        let input = if let Some((_, key)) = KEY_NAMES.iter().find(|(name, _)| name == input) {
+            Input::Key(Key::Named(*key))
+        } else if let Some(key) = function_key {
+            Input::Key(Key::Named(*key))
+        } else if let Some((_, button)) = MOUSE_NAMES.iter().find(|(name, _)| name == input) {
+            Input::Mouse(*button)
+        } else if let Some(button) = mouse_button {
+            Input::Mouse(button)
+        } else if *input == "plus" {
+            Input::Key(Key::Character("+".into()))
+        } else if *input == "minus" {
+            Input::Key(Key::Character("-".into()))
+        } else if input.chars().count() == 1 {
+            Input::Key(Key::Character(SmolStr::new(input)))
+        } else {
+            return Err(format!("unknown key `{input}` in `{s}`"));
+        };
+        Ok(Chord::new(modifiers, input))
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io(err) => write!(f, "can't read the bindings: {err}"),
+            BindingsError::Toml(err) => write!(f, "invalid bindings file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+impl From<io::Error> for BindingsError {
+    fn from(err: io::Error) -> Self {
+        BindingsError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for BindingsError {
+    fn from(err: toml::de::Error) -> Self {
+        BindingsError::Toml(err)
+    }
+}
+
+/// The actions keys and mouse buttons are bound to, and how far the navigation actions go.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "BindingsSpec")]
+pub struct Bindings {
+    bindings: HashMap<Chord, Action>,
+    /// Fraction of the smaller side of the window a pan step moves the image by
+    pub pan_step: f64,
+    /// Zoom factor of a zoom step
+    pub zoom_step: f64,
+    /// Zoom factor of a fine zoom step
+    pub fine_zoom_step: f64,
+    /// Angle in radians a rotation step turns the image by
+    pub rotation_step: f64,
+    /// Zoom factor of scrolling the mouse wheel by a line
+    pub wheel_zoom: f64,
+    /// Angle in radians the rotation snaps to multiples of, 0 for none
+    pub snap_angle: f64,
+    /// What dragging with ctrl held turns the view about
+    pub rotation_pivot: Pivot,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Self::empty();
+        let defaults = [
+            ("left", Action::PanLeft),
+            ("right", Action::PanRight),
+            ("up", Action::PanUp),
+            ("down", Action::PanDown),
+            ("plus", Action::ZoomIn),
+            ("=", Action::ZoomIn),
+            ("pageup", Action::ZoomIn),
+            ("minus", Action::ZoomOut),
+            ("pagedown", Action::ZoomOut),
+            ("alt+plus", Action::ZoomInFine),
+            ("alt+=", Action::ZoomInFine),
+            ("alt+pageup", Action::ZoomInFine),
+            ("alt+minus", Action::ZoomOutFine),
+            ("alt+pagedown", Action::ZoomOutFine),
+            ("e", Action::RotateCw),
+            ("ctrl+right", Action::RotateCw),
+            ("q", Action::RotateCcw),
+            ("ctrl+left", Action::RotateCcw),
+            ("r", Action::SnapRotation),
+            ("space", Action::Reset),
+            ("home", Action::Reset),
+            ("ctrl+z", Action::Undo),
+            ("super+z", Action::Undo),
+            ("mouse-back", Action::Undo),
+            ("ctrl+shift+z", Action::Redo),
+            ("super+shift+z", Action::Redo),
+            ("ctrl+y", Action::Redo),
+            ("mouse-forward", Action::Redo),
+            ("p", Action::Screenshot),
+            ("a", Action::CycleAntialiasing),
+            ("b", Action::Bookmark),
+            ("tab", Action::NextBookmark),
+            ("shift+tab", Action::PreviousBookmark),
+            ("h", Action::ToggleHud),
+            ("escape", Action::Cancel),
+        ];
+        for (chord, action) in defaults {
+            bindings.bind(chord.parse().unwrap(), Some(action));
+        }
+        for number in 1..=9 {
+            let chord = Chord::key(
+                ModifiersState::empty(),
+                Key::Character(number.to_string().into()),
+            );
+            bindings.bind(chord, Some(Action::JumpToBookmark(number - 1)));
+        }
+        bindings
+    }
+}
+
+impl Bindings {
+    /// No bindings at all, with the default steps.
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            pan_step: 0.1,
+            zoom_step: 1.5,
+            fine_zoom_step: 1.1,
+            rotation_step: 0.1 * TAU,
+            wheel_zoom: 1.05,
+            snap_angle: TAU / 24.0,
+            rotation_pivot: Pivot::Center,
+        }
+    }
+
+    /// Bind `chord` to `action`, or unbind it if there is none.
+    pub fn bind(&mut self, chord: Chord, action: Option<Action>) {
+        match action {
+            Some(action) => self.bindings.insert(chord, action),
+            None => self.bindings.remove(&chord),
+        };
+    }
+
+    /// The action bound to `chord`.
+    ///
+    /// Characters that need shift to be typed, like `+` on many layouts, still match bindings
+    /// without shift as long as there is no binding with it.
+    pub fn action(&self, chord: &Chord) -> Option<Action> {
+        let chord = Chord::new(chord.modifiers, chord.input.clone());
+        self.bindings.get(&chord).copied().or_else(|| {
+            let is_character = matches!(chord.input, Input::Key(Key::Character(_)));
+            if !is_character || !chord.modifiers.shift_key() {
+                return None;
+            }
+            let unshifted = Chord {
+                modifiers: chord.modifiers - ModifiersState::SHIFT,
+                ..chord
+            };
+            self.bindings.get(&unshifted).copied()
+        })
+    }
+
+    /// All bindings, sorted by action.
+    pub fn iter(&self) -> Vec<(&Chord, Action)> {
+        let mut bindings = self
+            .bindings
+            .iter()
+            .map(|(chord, action)| (chord, *action))
+            .collect::<Vec<_>>();
+        bindings.sort_by_key(|&(chord, action)| (action, chord.to_string()));
+        bindings
+    }
+
+    /// Where bindings are read from by default, if the platform has a config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                None
+            } else {
+                dirs::config_dir().map(|dir| dir.join("wgpu-mandelbrot").join("bindings.toml"))
+            }
+        }
+    }
+
+    /// Read the bindings file at `path`, or the defaults if there is none.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BindingsError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(text.parse()?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl FromStr for Bindings {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+/// A bindings file as it is written.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingsSpec {
+    pan_step: Option<f64>,
+    zoom_step: Option<f64>,
+    fine_zoom_step: Option<f64>,
+    /// In degrees
+    rotation_step: Option<f64>,
+    wheel_zoom: Option<f64>,
+    /// In degrees
+    snap_angle: Option<f64>,
+    rotation_pivot: Option<Pivot>,
+    #[serde(default)]
+    replace_defaults: bool,
+    #[serde(default)]
+    bindings: BTreeMap<String, String>,
+}
+
+impl TryFrom<BindingsSpec> for Bindings {
+    type Error = String;
+
+    fn try_from(spec: BindingsSpec) -> Result<Self, String> {
+        let mut bindings = if spec.replace_defaults {
+            Self::empty()
+        } else {
+            Self::default()
+        };
+        for (chord, action) in spec.bindings {
+            let action = match action.as_str() {
+                "none" => None,
+                action => Some(action.parse()?),
+            };
+            bindings.bind(chord.parse()?, action);
+        }
+        if let Some(pan_step) = spec.pan_step {
+            bindings.pan_step = pan_step;
+        }
+        if let Some(zoom_step) = spec.zoom_step {
+            bindings.zoom_step = zoom_step;
+        }
+        if let Some(fine_zoom_step) = spec.fine_zoom_step {
+            bindings.fine_zoom_step = fine_zoom_step;
+        }
+        if let Some(rotation_step) = spec.rotation_step {
+            bindings.rotation_step = rotation_step.to_radians();
+        }
+        if let Some(wheel_zoom) = spec.wheel_zoom {
+            bindings.wheel_zoom = wheel_zoom;
+        }
+        if let Some(snap_angle) = spec.snap_angle {
+            bindings.snap_angle = snap_angle.to_radians();
+        }
+        if let Some(rotation_pivot) = spec.rotation_pivot {
+            bindings.rotation_pivot = rotation_pivot;
+        }
+        for (name, step) in [
+            ("pan_step", bindings.pan_step),
+            ("rotation_step", bindings.rotation_step),
+            ("snap_angle", bindings.snap_angle),
+        ] {
+            if !step.is_finite() {
+                return Err(format!("{name} must be a finite number, not {step}"));
+            }
+        }
+        // zooming by a factor of 0 or less would collapse or mirror the view
+        for (name, factor) in [
+            ("zoom_step", bindings.zoom_step),
+            ("fine_zoom_step", bindings.fine_zoom_step),
+            ("wheel_zoom", bindings.wheel_zoom),
+        ] {
+            if !(factor.is_finite() && factor > 0.0) {
+                return Err(format!("{name} must be a positive number, not {factor}"));
+            }
+        }
+        Ok(bindings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chord_names() {
+        for name in [
+            "space",
+            "ctrl+shift+z",
+            "alt+super+f11",
+            "plus",
+            "ctrl+minus",
+            "mouse-back",
+            "shift+mouse-7",
+            "=",
+        ] {
+            let chord = name.parse::<Chord>().unwrap();
+            assert_eq!(chord.to_string(), name);
+        }
+        assert_eq!(
+            "Ctrl+Z".parse::<Chord>(),
+            Ok(Chord::key(
+                ModifiersState::CONTROL,
+                Key::Character("z".into())
+            ))
+        );
+        assert_eq!("ctrl++".parse::<Chord>(), "ctrl+plus".parse());
+        assert!("hyper+z".parse::<Chord>().is_err());
+        assert!("ctrl+nothing".parse::<Chord>().is_err());
+    }
+
+    #[test]
+    fn test_action_names() {
+        for (name, action) in Action::NAMES {
+            assert_eq!(name.parse(), Ok(action));
+            assert_eq!(action.to_string(), name);
+        }
+        assert_eq!(
+            "jump-to-bookmark-12".parse(),
+            Ok(Action::JumpToBookmark(11))
+        );
+        assert_eq!(Action::JumpToBookmark(0).to_string(), "jump-to-bookmark-1");
+        assert!("jump-to-bookmark-0".parse::<Action>().is_err());
+        assert!("fly".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn test_default_bindings() {
+        let bindings = Bindings::default();
+        let key =
+            |modifiers, c: &str| bindings.action(&Chord::key(modifiers, Key::Character(c.into())));
+        assert_eq!(key(ModifiersState::CONTROL, "z"), Some(Action::Undo));
+        // shift changes the character that is typed
+        let redo = ModifiersState::CONTROL | ModifiersState::SHIFT;
+        assert_eq!(key(redo, "Z"), Some(Action::Redo));
+        assert_eq!(key(ModifiersState::SHIFT, "+"), Some(Action::ZoomIn));
+        let fine = ModifiersState::ALT | ModifiersState::SHIFT;
+        assert_eq!(key(fine, "+"), Some(Action::ZoomInFine));
+        assert_eq!(
+            key(ModifiersState::empty(), "3"),
+            Some(Action::JumpToBookmark(2))
+        );
+        assert_eq!(key(ModifiersState::CONTROL, "a"), None);
+        assert_eq!(
+            bindings.action(&Chord::mouse(ModifiersState::empty(), MouseButton::Back)),
+            Some(Action::Undo)
+        );
+        // keyboard only navigation
+        for action in [
+            Action::PanLeft,
+            Action::PanRight,
+            Action::PanUp,
+            Action::PanDown,
+            Action::ZoomIn,
+            Action::ZoomOut,
+            Action::RotateCw,
+            Action::RotateCcw,
+        ] {
+            assert!(bindings
+                .iter()
+                .iter()
+                .any(|&(chord, it)| it == action && matches!(chord.input, Input::Key(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse_bindings() {
+        let bindings: Bindings = r#"
+            rotation_step = 90
+            wheel_zoom = 1.1
+            snap_angle = 45
+            rotation_pivot = "cursor"
+
+            [bindings]
+            "w" = "pan-up"
+            "space" = "none"
+            "mouse-middle" = "reset"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(bindings.rotation_step, TAU / 4.0);
+        assert_eq!(bindings.wheel_zoom, 1.1);
+        assert_eq!(bindings.snap_angle, TAU / 8.0);
+        assert_eq!(bindings.rotation_pivot, Pivot::Cursor);
+        assert_eq!(bindings.pan_step, Bindings::default().pan_step);
+        let chord = |name: &str| name.parse::<Chord>().unwrap();
+        assert_eq!(bindings.action(&chord("w")), Some(Action::PanUp));
+        assert_eq!(bindings.action(&chord("space")), None);
+        assert_eq!(bindings.action(&chord("mouse-middle")), Some(Action::Reset));
+        assert_eq!(bindings.action(&chord("home")), Some(Action::Reset));
+
+        let replaced: Bindings = "replace_defaults = true\n[bindings]\nx = \"undo\""
+            .parse()
+            .unwrap();
+        assert_eq!(replaced.iter().len(), 1);
+        assert!("[bindings]\nx = \"fly\"".parse::<Bindings>().is_err());
+        assert!("speed = 2".parse::<Bindings>().is_err());
+        assert!("rotation_pivot = \"corner\"".parse::<Bindings>().is_err());
+        for steps in [
+            "zoom_step = 0",
+            "fine_zoom_step = -1.1",
+            "wheel_zoom = inf",
+            "pan_step = nan",
+            "snap_angle = -inf",
+        ] {
+            assert!(steps.parse::<Bindings>().is_err(), "{steps}");
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use kurbo::Affine;
+use wgpu::{util::DeviceExt as _, BindGroup, BindGroupLayout, Device, Queue, TextureFormat};
+
+use crate::transform_from_affine;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlitUniform {
+    transform: [f32; 6],
+    _padding: [f32; 2], // Padding to ensure 16-byte alignment
+    region: [f32; 2],
+    _padding2: [f32; 2], // Padding to ensure 16-byte alignment
+}
+
+impl BlitUniform {
+    fn new(transform: Affine, region: [f32; 2]) -> Self {
+        Self {
+            transform: transform_from_affine(transform),
+            _padding: [0.0, 0.0],
+            region,
+            _padding2: [0.0, 0.0],
+        }
+    }
+}
+
+/// Copies one texture onto the whole of another with a full screen triangle.
+///
+/// The copy can be restricted to the top left part of the source texture (see
+/// [`BlitSource::set_uv_scale`]) which is how coarse refinement passes, which only fill part of
+/// their texture, get stretched over the full window. It can also be warped by an arbitrary
+/// transform (see [`BlitSource::set_uv_transform`]) to reuse an old image as a placeholder after
+/// the view changed.
+pub(crate) struct Blitter {
+    pipeline: wgpu::RenderPipeline,
+    present_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+/// A texture bound for use with a [`Blitter`].
+pub(crate) struct BlitSource {
+    uniform_buffer: wgpu::Buffer,
+    bind_group: BindGroup,
+}
+
+impl BlitSource {
+    /// Set the fraction of the source texture, measured from the top left, that is copied.
+    pub(crate) fn set_uv_scale(&self, queue: &Queue, uv_scale: [f32; 2]) {
+        let transform = Affine::scale_non_uniform(uv_scale[0] as f64, uv_scale[1] as f64);
+        let uniform = BlitUniform::new(transform, uv_scale);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Set the transform from texture coordinates of the target to texture coordinates of the
+    /// whole source texture. Parts of the target that map to outside of the source are cleared.
+    pub(crate) fn set_uv_transform(&self, queue: &Queue, transform: Affine) {
+        let uniform = BlitUniform::new(transform, [1.0, 1.0]);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+}
+
+impl Blitter {
+    pub(crate) fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("blit.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label, entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let pipeline = create_pipeline("Blit Pipeline", "fs_main");
+        let present_pipeline = create_pipeline("Present Pipeline", "fs_present");
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blit Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            present_pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    pub(crate) fn source(&self, device: &Device, texture: &wgpu::TextureView) -> BlitSource {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Blit Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[BlitUniform::new(Affine::IDENTITY, [1.0, 1.0])]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        BlitSource {
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    /// Record a pass that covers all of `target` with the contents of `source`.
+    pub(crate) fn blit(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &BlitSource,
+        target: &wgpu::TextureView,
+    ) {
+        self.record(encoder, &self.pipeline, source, target);
+    }
+
+    /// Like [`Blitter::blit`] but makes the result opaque, for showing the accumulated image in
+    /// the window.
+    pub(crate) fn present(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &BlitSource,
+        target: &wgpu::TextureView,
+    ) {
+        self.record(encoder, &self.present_pipeline, source, target);
+    }
+
+    fn record(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        source: &BlitSource,
+        target: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            ..Default::default()
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &source.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
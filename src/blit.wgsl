@@ -0,0 +1,67 @@
+struct Affine {
+    elements: array<vec4<f32>, 2>,
+};
+
+fn transform_point(affine: Affine, point: vec2<f32>) -> vec2<f32> {
+    let a = affine.elements[0].x;
+    let b = affine.elements[0].y;
+    let c = affine.elements[0].z;
+    let d = affine.elements[0].w;
+    let e = affine.elements[1].x;
+    let f = affine.elements[1].y;
+
+    return vec2<f32>(a * point.x + c * point.y + e, b * point.x + d * point.y + f);
+}
+
+struct Blit {
+    // maps texture coordinates of the target to texture coordinates of the source
+    transform: Affine,
+    // fraction of the source texture, from the top left, that holds the image being copied
+    region: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> blit: Blit;
+@group(0) @binding(1)
+var source: texture_2d<f32>;
+@group(0) @binding(2)
+var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// A single triangle that covers the whole target, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    out.pos = vec4<f32>(x, y, 0.0, 1.0);
+    // texture coordinates grow downwards, clip space grows upwards
+    out.uv = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+    return out;
+}
+
+fn sample_source(in: VertexOutput) -> vec4<f32> {
+    let uv = transform_point(blit.transform, in.uv);
+    if (any(uv < vec2<f32>(0.0)) || any(uv > blit.region)) {
+        // nothing was rendered here yet
+        return vec4<f32>(0.0);
+    }
+    // keep linear filtering from bleeding in texels outside of the copied region
+    let half_texel = 0.5 / vec2<f32>(textureDimensions(source));
+    return textureSampleLevel(source, source_sampler, clamp(uv, half_texel, blit.region - half_texel), 0.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return sample_source(in);
+}
+
+// The accumulated image keeps iteration counts in alpha, the window should only see the color
+@fragment
+fn fs_present(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(sample_source(in).rgb, 1.0);
+}
@@ -0,0 +1,519 @@
+//! Named locations that are kept between sessions.
+//!
+//! # Bookmark file format
+//!
+//! Bookmarks are stored as TOML in the user's config directory (see [`Bookmarks::default_path`]),
+//! with the version of the format and a list of bookmarks under `bookmark`:
+//!
+//! ```toml
+//! version = 1
+//!
+//! [[bookmark]]
+//! name = "Seahorse Valley"
+//! center = [-0.7453, 0.1127]
+//! zoom = 100.0
+//! rotation = 0.0
+//! notes = "Spirals between the main cardioid and the period 2 bulb"
+//! thumbnail = "thumbnails/seahorse-valley-4b3c0404.png"
+//! ```
+//!
+//! `rotation`, `notes` and `thumbnail` can be left out. Thumbnails are paths relative to the
+//! directory of the file. Until the file is first saved, the built-in [`Bookmarks::famous`]
+//! locations stand in for it.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use kurbo::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::view::View;
+
+/// A location with a name, and optionally notes and a small image of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub view: View,
+    pub notes: String,
+    /// Path of a PNG showing the location, relative to the directory of the bookmark file
+    pub thumbnail: Option<PathBuf>,
+}
+
+impl Bookmark {
+    pub fn new(name: impl Into<String>, view: View) -> Self {
+        Self {
+            name: name.into(),
+            view,
+            notes: String::new(),
+            thumbnail: None,
+        }
+    }
+
+    pub fn with_notes(self, notes: impl Into<String>) -> Self {
+        Self {
+            notes: notes.into(),
+            ..self
+        }
+    }
+
+    /// Whether every whitespace separated word of `query` is in the name or notes, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let name = self.name.to_lowercase();
+        let notes = self.notes.to_lowercase();
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| name.contains(word) || notes.contains(word))
+    }
+}
+
+#[derive(Debug)]
+pub enum BookmarkError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The file was written by a newer version that stores bookmarks differently
+    Version(u32),
+    #[cfg(not(target_arch = "wasm32"))]
+    Thumbnail(png::EncodingError),
+}
+
+impl fmt::Display for BookmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookmarkError::Io(err) => write!(f, "can't access the bookmarks: {err}"),
+            BookmarkError::Parse(err) => write!(f, "invalid bookmark file: {err}"),
+            BookmarkError::Serialize(err) => write!(f, "can't write the bookmarks: {err}"),
+            BookmarkError::Version(version) => write!(
+                f,
+                "bookmark file version {version} is newer than the supported version {}",
+                Bookmarks::VERSION
+            ),
+            #[cfg(not(target_arch = "wasm32"))]
+            BookmarkError::Thumbnail(err) => write!(f, "can't save the thumbnail: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BookmarkError {}
+
+impl From<io::Error> for BookmarkError {
+    fn from(err: io::Error) -> Self {
+        BookmarkError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for BookmarkError {
+    fn from(err: toml::de::Error) -> Self {
+        BookmarkError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for BookmarkError {
+    fn from(err: toml::ser::Error) -> Self {
+        BookmarkError::Serialize(err)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<png::EncodingError> for BookmarkError {
+    fn from(err: png::EncodingError) -> Self {
+        BookmarkError::Thumbnail(err)
+    }
+}
+
+/// An ordered list of bookmarks with unique names.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bookmarks {
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// The version of the file format that is written
+    pub const VERSION: u32 = 1;
+    /// Size of the thumbnails that are saved with bookmarks
+    pub const THUMBNAIL_SIZE: (u32, u32) = (192, 108);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Well known locations, which are the bookmarks before any are saved.
+    pub fn famous() -> Self {
+        let bookmark = |name: &str, [x, y]: [f64; 2], zoom, notes: &str| {
+            Bookmark::new(name, View::new(Vec2::new(x, y), zoom)).with_notes(notes)
+        };
+        Self {
+            bookmarks: vec![
+                bookmark(
+                    "Seahorse Valley",
+                    [-0.7453, 0.1127],
+                    100.0,
+                    "Spirals between the main cardioid and the period 2 bulb",
+                ),
+                bookmark(
+                    "Elephant Valley",
+                    [0.285, 0.0135],
+                    100.0,
+                    "Trunks curling out of the cusp of the main cardioid",
+                ),
+                bookmark(
+                    "Triple Spiral Valley",
+                    [-0.088, 0.654],
+                    60.0,
+                    "Three armed spirals next to the period 3 bulb",
+                ),
+                bookmark(
+                    "Period 3 minibrot",
+                    [-1.7549, 0.0],
+                    40.0,
+                    "The largest copy of the set on the real axis",
+                ),
+                bookmark(
+                    "Misiurewicz point at i",
+                    [0.0, 1.0],
+                    20.0,
+                    "Misiurewicz point, 0 falls onto a cycle of period 2",
+                ),
+                bookmark(
+                    "Misiurewicz point at -2",
+                    [-2.0, 0.0],
+                    20.0,
+                    "Misiurewicz point at the tip of the antenna, 0 falls onto the fixed point 2",
+                ),
+                bookmark(
+                    "Misiurewicz spiral",
+                    [-0.1011, 0.9563],
+                    200.0,
+                    "Misiurewicz point where 0 falls onto a fixed point after three steps",
+                ),
+            ],
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bookmark> {
+        self.bookmarks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|it| it.name == name)
+    }
+
+    /// The bookmarks that match `query`, see [`Bookmark::matches`].
+    pub fn search<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a Bookmark> {
+        self.bookmarks.iter().filter(move |it| it.matches(query))
+    }
+
+    /// Add `bookmark` at the end, or in place of the one with the same name which is returned.
+    pub fn add(&mut self, bookmark: Bookmark) -> Option<Bookmark> {
+        match self
+            .bookmarks
+            .iter_mut()
+            .find(|it| it.name == bookmark.name)
+        {
+            Some(existing) => Some(std::mem::replace(existing, bookmark)),
+            None => {
+                self.bookmarks.push(bookmark);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Bookmark> {
+        let index = self.bookmarks.iter().position(|it| it.name == name)?;
+        Some(self.bookmarks.remove(index))
+    }
+
+    /// A name like `Bookmark 3` that no bookmark has yet.
+    pub fn unused_name(&self) -> String {
+        (self.bookmarks.len() + 1..)
+            .map(|n| format!("Bookmark {n}"))
+            .find(|name| self.get(name).is_none())
+            .unwrap()
+    }
+
+    /// Where bookmarks are kept by default, if the platform has a config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                None
+            } else {
+                dirs::config_dir().map(|dir| dir.join("wgpu-mandelbrot").join("bookmarks.toml"))
+            }
+        }
+    }
+
+    /// Read the bookmark file at `path`, or the famous locations if there is none yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BookmarkError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::famous()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Write the bookmarks to `path`, creating its directory if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BookmarkError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, BookmarkError> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        // later versions may store bookmarks in ways this one can't read at all
+        let Version { version } = toml::from_str(text)?;
+        if version > Self::VERSION {
+            return Err(BookmarkError::Version(version));
+        }
+        let file: BookmarkFile = toml::from_str(text)?;
+        Ok(Self {
+            bookmarks: file.bookmarks.into_iter().map(Bookmark::from).collect(),
+        })
+    }
+
+    pub fn to_toml(&self) -> Result<String, BookmarkError> {
+        let file = BookmarkFile {
+            version: Self::VERSION,
+            bookmarks: self.bookmarks.iter().map(BookmarkSpec::from).collect(),
+        };
+        Ok(toml::to_string(&file)?)
+    }
+
+    /// Render a thumbnail of `bookmark` on the CPU and save it next to the bookmark file at
+    /// `path`, pointing the bookmark at it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_thumbnail(
+        bookmark: &mut Bookmark,
+        path: impl AsRef<Path>,
+        settings: &crate::settings::Settings,
+    ) -> Result<(), BookmarkError> {
+        let (width, height) = Self::THUMBNAIL_SIZE;
+        let image = crate::cpu::render(&bookmark.view, settings, width, height);
+        let thumbnail = Path::new("thumbnails").join(thumbnail_name(&bookmark.name));
+        let dir = path.as_ref().parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir.join("thumbnails"))?;
+        // the thumbnail can be dropped on the window like any exported image
+        let metadata = crate::metadata::Metadata::new(bookmark.view, settings, width, height);
+        image.save_png(dir.join(&thumbnail), Some(&metadata))?;
+        bookmark.thumbnail = Some(thumbnail);
+        Ok(())
+    }
+}
+
+/// The file name of the thumbnail of the bookmark called `name`.
+///
+/// The name is made readable by keeping only letters and digits, so a hash of the whole name is
+/// added to tell apart names like `Seahorse: tail` and `Seahorse tail` that differ otherwise.
+#[cfg(not(target_arch = "wasm32"))]
+fn thumbnail_name(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    // FNV-1a, which unlike the standard library's hasher is the same in every build
+    let hash = name.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+    format!("{slug}-{hash:08x}.png")
+}
+
+/// The bookmark file as it is written.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BookmarkFile {
+    version: u32,
+    #[serde(rename = "bookmark", default)]
+    bookmarks: Vec<BookmarkSpec>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BookmarkSpec {
+    name: String,
+    center: [f64; 2],
+    zoom: f64,
+    #[serde(default)]
+    rotation: f64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<PathBuf>,
+}
+
+impl From<BookmarkSpec> for Bookmark {
+    fn from(spec: BookmarkSpec) -> Self {
+        let [x, y] = spec.center;
+        Self {
+            name: spec.name,
+            view: View {
+                center: Vec2::new(x, y),
+                zoom: spec.zoom,
+                rotation: spec.rotation,
+            },
+            notes: spec.notes,
+            thumbnail: spec.thumbnail,
+        }
+    }
+}
+
+impl From<&Bookmark> for BookmarkSpec {
+    fn from(bookmark: &Bookmark) -> Self {
+        Self {
+            name: bookmark.name.clone(),
+            center: [bookmark.view.center.x, bookmark.view.center.y],
+            zoom: bookmark.view.zoom,
+            rotation: bookmark.view.rotation,
+            notes: bookmark.notes.clone(),
+            thumbnail: bookmark.thumbnail.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut bookmarks = Bookmarks::famous();
+        let mut bookmark = Bookmark::new("Deep", View::new(Vec2::new(-1.25, 0.02), 1e6));
+        bookmark.view.rotation = 0.5;
+        bookmark.thumbnail = Some(PathBuf::from("thumbnails/deep.png"));
+        bookmarks.add(bookmark);
+        let text = bookmarks.to_toml().unwrap();
+        assert!(text.starts_with("version = 1\n"), "{text}");
+        assert_eq!(Bookmarks::from_toml(&text).unwrap(), bookmarks);
+    }
+
+    #[test]
+    fn test_parse() {
+        let bookmarks = Bookmarks::from_toml(
+            r#"
+            version = 1
+
+            [[bookmark]]
+            name = "Spiral"
+            center = [-0.7453, 0.1127]
+            zoom = 100
+            "#,
+        )
+        .unwrap();
+        let spiral = bookmarks.get("Spiral").unwrap();
+        assert_eq!(spiral.view, View::new(Vec2::new(-0.7453, 0.1127), 100.0));
+        assert_eq!((spiral.notes.as_str(), &spiral.thumbnail), ("", &None));
+
+        assert!(matches!(
+            Bookmarks::from_toml("version = 2\nsomething_else = true"),
+            Err(BookmarkError::Version(2))
+        ));
+        assert!(matches!(
+            Bookmarks::from_toml("[[bookmark]]\nname = \"a\""),
+            Err(BookmarkError::Parse(_))
+        ));
+        assert_eq!(
+            Bookmarks::from_toml("version = 1").unwrap(),
+            Bookmarks::new()
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let bookmarks = Bookmarks::famous();
+        let names = |query| {
+            bookmarks
+                .search(query)
+                .map(|it| it.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("valley SEAHORSE"), ["Seahorse Valley"]);
+        // notes are searched too
+        assert_eq!(names("antenna"), ["Misiurewicz point at -2"]);
+        assert_eq!(names("").len(), bookmarks.len());
+        assert!(names("nowhere").is_empty());
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut bookmarks = Bookmarks::new();
+        let name = bookmarks.unused_name();
+        assert_eq!(name, "Bookmark 1");
+        assert_eq!(bookmarks.add(Bookmark::new(&name, View::default())), None);
+        let moved = Bookmark::new(&name, View::new(Vec2::ZERO, 2.0));
+        assert!(bookmarks.add(moved.clone()).is_some());
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks.get(&name), Some(&moved));
+        assert_eq!(bookmarks.unused_name(), "Bookmark 2");
+        assert_eq!(bookmarks.remove(&name), Some(moved));
+        assert!(bookmarks.is_empty());
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = std::env::temp_dir().join(format!("bookmarks-test-{}", std::process::id()));
+        let path = dir.join("config").join("bookmarks.toml");
+        // nothing saved yet
+        assert_eq!(Bookmarks::load(&path).unwrap(), Bookmarks::famous());
+
+        let mut bookmarks = Bookmarks::new();
+        let mut bookmark = Bookmark::new(
+            "Seahorse: tail",
+            View::new(Vec2::new(-0.7453, 0.1127), 50.0),
+        );
+        let settings = crate::settings::Settings::default();
+        Bookmarks::save_thumbnail(&mut bookmark, &path, &settings).unwrap();
+        let thumbnail = bookmark.thumbnail.clone().unwrap();
+        assert_eq!(
+            thumbnail,
+            Path::new("thumbnails").join(thumbnail_name("Seahorse: tail"))
+        );
+        let image = crate::image::Image::load_png(path.parent().unwrap().join(&thumbnail)).unwrap();
+        assert_eq!((image.width, image.height), Bookmarks::THUMBNAIL_SIZE);
+        bookmarks.add(bookmark);
+        bookmarks.save(&path).unwrap();
+        assert_eq!(Bookmarks::load(&path).unwrap(), bookmarks);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_thumbnail_names_differ() {
+        let names = [
+            "Seahorse: tail",
+            "Seahorse  tail",
+            "seahorse--tail",
+            "Seahorse tail",
+        ];
+        let mut thumbnails: Vec<String> = names.iter().map(|it| thumbnail_name(it)).collect();
+        assert!(thumbnails[0].starts_with("seahorse--tail-"));
+        thumbnails.sort();
+        thumbnails.dedup();
+        assert_eq!(thumbnails.len(), names.len());
+        // the same name always gets the same file, so saving a bookmark again replaces it
+        assert_eq!(
+            thumbnail_name("Seahorse tail"),
+            thumbnail_name("Seahorse tail")
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use web_time::{Duration, Instant};
+use wgpu::{Device, Queue};
+
+use crate::tiles::Tile;
+
+/// Decides how much of the fractal can be rendered in a single frame.
+///
+/// Work is measured in samples at the full iteration budget, so a tile of a refinement pass that
+/// only spends half of the iterations counts as half of its area and a supersampled one as its
+/// area times the number of samples per pixel. After every measured frame the
+/// estimated cost of one unit of work is updated, which keeps the amount of work submitted per
+/// frame close to `target` even as the iteration count grows with the zoom. Keeping submissions
+/// short stops the driver from deciding that the GPU hung and resetting it.
+#[derive(Debug)]
+pub struct FrameBudget {
+    target: Duration,
+    /// Estimated GPU time in seconds for one unit of work
+    cost_per_work: f64,
+}
+
+impl FrameBudget {
+    /// Start out assuming a slow GPU so the very first frames can't stall it.
+    const INITIAL_COST_PER_WORK: f64 = 50e-9;
+
+    pub fn new(target: Duration) -> Self {
+        Self {
+            target,
+            cost_per_work: Self::INITIAL_COST_PER_WORK,
+        }
+    }
+
+    /// How many of `tiles`, taken in order, fit into one frame.
+    ///
+    /// At least one tile is always allowed so that rendering makes progress.
+    pub fn tiles_per_frame<'a>(
+        &self,
+        tiles: impl IntoIterator<Item = &'a Tile>,
+        iteration_scale: f32,
+        samples: u32,
+    ) -> usize {
+        let budget = self.target.as_secs_f64();
+        let mut spent = 0.0;
+        let mut count = 0;
+        for tile in tiles {
+            spent += work(tile, iteration_scale, samples) * self.cost_per_work;
+            if spent > budget && count > 0 {
+                break;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Feed back how long `work` units of work actually took.
+    pub fn record(&mut self, elapsed: Duration, work: f64) {
+        if work <= 0.0 {
+            return;
+        }
+        let measured = elapsed.as_secs_f64() / work;
+        // smooth out the noise between frames while still adapting within a few frames
+        self.cost_per_work = 0.5 * self.cost_per_work + 0.5 * measured;
+    }
+}
+
+/// The amount of work it takes to render `tile` with `iteration_scale` of the iteration budget
+/// and `samples` by `samples` samples per pixel.
+pub fn work(tile: &Tile, iteration_scale: f32, samples: u32) -> f64 {
+    tile.area() as f64 * iteration_scale as f64 * (samples as f64).powi(2)
+}
+
+/// Buffers for reading back GPU timestamps written at the start and end of a render pass.
+struct Timestamps {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    mapped: Arc<AtomicBool>,
+    /// Nanoseconds per timestamp tick
+    period: f64,
+}
+
+/// Measures how long the GPU spends on the submitted work of a frame.
+///
+/// Timestamp queries are used when the device supports them. Otherwise the time between
+/// submitting and the queue reporting the work as done is used, which also includes some
+/// scheduling overhead but is available everywhere. Only one measurement is in flight at a time;
+/// results arrive a frame or two later through [`GpuTimer::finished`].
+pub(crate) struct GpuTimer {
+    timestamps: Option<Timestamps>,
+    submitted_at: Instant,
+    finished_at: Arc<Mutex<Option<Instant>>>,
+    /// The work of the measurement in flight, if any
+    in_flight: Option<f64>,
+}
+
+impl GpuTimer {
+    pub(crate) fn new(device: &Device, queue: &Queue) -> Self {
+        let timestamps = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| {
+                let size = 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+                Timestamps {
+                    query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                        label: Some("Frame Timestamps"),
+                        ty: wgpu::QueryType::Timestamp,
+                        count: 2,
+                    }),
+                    resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Timestamp Resolve Buffer"),
+                        size,
+                        usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                        mapped_at_creation: false,
+                    }),
+                    readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Timestamp Readback Buffer"),
+                        size,
+                        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }),
+                    mapped: Arc::new(AtomicBool::new(false)),
+                    period: queue.get_timestamp_period() as f64,
+                }
+            });
+
+        Self {
+            timestamps,
+            submitted_at: Instant::now(),
+            finished_at: Arc::new(Mutex::new(None)),
+            in_flight: None,
+        }
+    }
+
+    /// Whether a new measurement can be started this frame.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.in_flight.is_none()
+    }
+
+    /// Timestamp writes for the render pass that is being measured.
+    pub(crate) fn timestamp_writes(&self) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.timestamps
+            .as_ref()
+            .map(|timestamps| wgpu::RenderPassTimestampWrites {
+                query_set: &timestamps.query_set,
+                beginning_of_pass_write_index: Some(0),
+                end_of_pass_write_index: Some(1),
+            })
+    }
+
+    /// Record copying the timestamps somewhere they can be read from. Call this after the
+    /// measured render pass has ended.
+    pub(crate) fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(timestamps) = &self.timestamps {
+            encoder.resolve_query_set(&timestamps.query_set, 0..2, &timestamps.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(
+                &timestamps.resolve_buffer,
+                0,
+                &timestamps.readback_buffer,
+                0,
+                timestamps.resolve_buffer.size(),
+            );
+        }
+    }
+
+    /// Start waiting for the measurement of `work` units of work that were just submitted.
+    pub(crate) fn submitted(&mut self, queue: &Queue, work: f64) {
+        self.in_flight = Some(work);
+        if let Some(timestamps) = &self.timestamps {
+            let mapped = timestamps.mapped.clone();
+            timestamps
+                .readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    mapped.store(result.is_ok(), Ordering::Release);
+                });
+        } else {
+            self.submitted_at = Instant::now();
+            let finished_at = self.finished_at.clone();
+            queue.on_submitted_work_done(move || {
+                *finished_at.lock().unwrap() = Some(Instant::now());
+            });
+        }
+    }
+
+    /// The measurement in flight, as elapsed time and the amount of work, once it is available.
+    ///
+    /// The device needs to be polled for the measurement to make progress.
+    pub(crate) fn finished(&mut self) -> Option<(Duration, f64)> {
+        let work = self.in_flight?;
+        let elapsed = if let Some(timestamps) = &self.timestamps {
+            if !timestamps.mapped.swap(false, Ordering::Acquire) {
+                return None;
+            }
+            let ticks = {
+                let data = timestamps.readback_buffer.slice(..).get_mapped_range();
+                let ticks: &[u64] = bytemuck::cast_slice(&data);
+                ticks[1].saturating_sub(ticks[0])
+            };
+            timestamps.readback_buffer.unmap();
+            Duration::from_nanos((ticks as f64 * timestamps.period) as u64)
+        } else {
+            let finished_at = self.finished_at.lock().unwrap().take()?;
+            finished_at - self.submitted_at
+        };
+        self.in_flight = None;
+        Some((elapsed, work))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::antialiasing::Antialiasing;
+
+    #[test]
+    fn test_budget_adapts_to_measurements() {
+        let tiles = crate::tiles::split(1024, 1024, 128);
+        let mut budget = FrameBudget::new(Duration::from_millis(10));
+        // one tile is always allowed even if it doesn't fit
+        budget.record(Duration::from_secs(1), 1.0);
+        assert_eq!(budget.tiles_per_frame(&tiles, 1.0, 1), 1);
+
+        // 1ns per pixel makes 10ms worth 10 million pixels, more than all 64 tiles
+        for _ in 0..64 {
+            budget.record(Duration::from_millis(1), 1e6);
+        }
+        assert_eq!(budget.tiles_per_frame(&tiles, 1.0, 1), tiles.len());
+    }
+
+    #[test]
+    fn test_supersampling_costs_every_sample() {
+        let tiles = crate::tiles::split(2048, 2048, 128);
+        let mut budget = FrameBudget::new(Duration::from_millis(10));
+        // 10ms is worth a bit over 160 tiles of 128 by 128 samples
+        budget.cost_per_work = 0.01 / (160.5 * 128.0 * 128.0);
+        let samples = Antialiasing::Supersample(4).samples();
+        assert_eq!(budget.tiles_per_frame(&tiles, 1.0, 1), 160);
+        assert_eq!(budget.tiles_per_frame(&tiles, 1.0, samples), 160 / 16);
+    }
+}
@@ -0,0 +1,925 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use clap::{Args, Parser, Subcommand};
+use kurbo::Vec2;
+use wgpu_mandelbrot::{
+    animation::Animation,
+    antialiasing::Antialiasing,
+    batch::{Batch, Job},
+    bindings::Bindings,
+    bookmarks::{Bookmark, Bookmarks},
+    data::PixelData,
+    exp_map::ExpMap,
+    headless::Renderer,
+    metadata::Metadata,
+    orbit::Orbit,
+    palette::Palette,
+    settings::Settings,
+    video::{VideoFormat, VideoWriter},
+    view::View,
+};
+
+/// Explore the Mandelbrot set or render it without a window.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// What to do, opens the window when left out
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Open the window at a location
+    Explore {
+        #[command(flatten)]
+        view: ViewArgs,
+        #[command(flatten)]
+        settings: SettingsArgs,
+    },
+    /// Render a location into an image file
+    Render(RenderArgs),
+    /// Color the per-pixel data of a `.raw` file with another palette, without iterating again
+    Recolor(RecolorArgs),
+    /// Render every job of a job file
+    Batch(BatchArgs),
+    /// Render the frames of a keyframe animation to a numbered PNG sequence
+    Animate(AnimateArgs),
+    /// Render a keyframe animation into an uncompressed video stream
+    Video(VideoArgs),
+    /// Render a zoom video by reprojecting its frames from one exponential map
+    ExpMap(ExpMapArgs),
+    /// Print what happens to a single point under iteration
+    Info(InfoArgs),
+    /// Time rendering a few well known locations
+    Bench(BenchArgs),
+    /// List, search, add or remove bookmarks
+    Bookmarks(BookmarksArgs),
+    /// Print what keys and mouse buttons do in the window
+    Bindings(BindingsArgs),
+}
+
+/// Which part of the set is shown, see [`View`].
+#[derive(Debug, Args)]
+struct ViewArgs {
+    /// Start from the view stored in an image exported by this app
+    #[arg(long, value_name = "PNG")]
+    from: Option<PathBuf>,
+    /// Start from the first bookmark that matches a search
+    #[arg(long, value_name = "SEARCH", conflicts_with = "from")]
+    bookmark: Option<String>,
+    /// Point at the center of the image, as `re,im`
+    #[arg(long, value_parser = parse_complex, allow_hyphen_values = true)]
+    center: Option<Vec2>,
+    /// Magnification relative to the whole set
+    #[arg(long)]
+    zoom: Option<f64>,
+    /// Rotation in radians
+    #[arg(long, allow_hyphen_values = true)]
+    rotation: Option<f64>,
+}
+
+impl ViewArgs {
+    /// The view and, with `--from`, the metadata it was read from.
+    fn load(&self) -> Result<(View, Option<Metadata>), Box<dyn Error>> {
+        let metadata = self.from.as_ref().map(Metadata::load_png).transpose()?;
+        let base = match &self.bookmark {
+            Some(query) => {
+                let bookmarks = load_bookmarks(None)?;
+                let bookmark = bookmarks
+                    .search(query)
+                    .next()
+                    .ok_or_else(|| format!("no bookmark matches `{query}`"))?;
+                bookmark.view
+            }
+            None => metadata.as_ref().map_or_else(View::default, |it| it.view),
+        };
+        let view = View {
+            center: self.center.unwrap_or(base.center),
+            zoom: self.zoom.unwrap_or(base.zoom),
+            rotation: self.rotation.unwrap_or(base.rotation),
+        };
+        Ok((view, metadata))
+    }
+}
+
+/// How the set is rendered, see [`Settings`].
+#[derive(Debug, Args)]
+struct SettingsArgs {
+    /// Colors as `offset r g b; amplitude r g b; frequency f; phase r g b`, left out parts keep
+    /// their defaults
+    #[arg(long)]
+    palette: Option<Palette>,
+    /// Iteration limit, raised with the zoom level when left out
+    #[arg(long)]
+    iterations: Option<u32>,
+    /// `off` or `supersample:N`, and in the window also `jitter:N` or `adaptive:N`
+    #[arg(long, value_parser = parse_antialiasing)]
+    antialiasing: Option<Antialiasing>,
+}
+
+impl SettingsArgs {
+    /// The settings, with anything not given on the command line taken from `metadata`.
+    fn settings(&self, metadata: Option<&Metadata>) -> Settings {
+        let defaults = metadata.map_or_else(Settings::default, |it| it.apply(Settings::default()));
+        Settings {
+            palette: self.palette.unwrap_or(defaults.palette),
+            max_iterations: self.iterations.or(defaults.max_iterations),
+            antialiasing: self.antialiasing.unwrap_or(defaults.antialiasing),
+        }
+    }
+
+    /// Like [`SettingsArgs::settings`] for rendering without a window, which can only supersample
+    /// since jittering and adaptive antialiasing refine the image over many frames.
+    fn still_settings(&self, metadata: Option<&Metadata>) -> Result<Settings, String> {
+        let settings = self.settings(metadata);
+        match settings.antialiasing {
+            Antialiasing::Jitter(_) | Antialiasing::Adaptive(_) => Err(
+                "jitter and adaptive antialiasing only work in the window, use supersample:N"
+                    .to_string(),
+            ),
+            Antialiasing::Off | Antialiasing::Supersample(_) => Ok(settings),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct RenderArgs {
+    #[command(flatten)]
+    view: ViewArgs,
+    #[command(flatten)]
+    settings: SettingsArgs,
+    #[arg(long, default_value_t = 1920)]
+    width: u32,
+    #[arg(long, default_value_t = 1080)]
+    height: u32,
+    /// Bits per channel of a PNG, 8 or 16 which is rendered on the CPU
+    #[arg(long, default_value_t = 8)]
+    depth: u8,
+    /// Render on the CPU even if there is a GPU
+    #[arg(long)]
+    cpu: bool,
+    /// Image file to write, `.png`, `.exr` or `.raw` for the raw per-pixel data
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct RecolorArgs {
+    /// Raw per-pixel data, as written by `render` or `batch` to a `.raw` file
+    input: PathBuf,
+    /// Colors as `offset r g b; amplitude r g b; frequency f; phase r g b`, left out parts keep
+    /// their defaults
+    #[arg(long)]
+    palette: Option<Palette>,
+    /// Bits per channel of a PNG, 8 or 16
+    #[arg(long, default_value_t = 8)]
+    depth: u8,
+    /// Image file to write, `.png` or `.exr`
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct BatchArgs {
+    /// TOML or JSON file listing the images to render
+    jobs: PathBuf,
+    /// Render on the CPU even if there is a GPU
+    #[arg(long)]
+    cpu: bool,
+}
+
+/// A keyframe file and what to change about it, see [`Animation`].
+#[derive(Debug, Args)]
+struct AnimationArgs {
+    /// TOML file with the keyframes
+    keyframes: PathBuf,
+    /// Frames per second instead of the one in the keyframe file
+    #[arg(long)]
+    frame_rate: Option<f64>,
+    /// Number of images blended into every frame for motion blur
+    #[arg(long)]
+    motion_blur: Option<u32>,
+    /// Degrees of the time between frames that motion blur spreads a frame over
+    #[arg(long)]
+    shutter_angle: Option<f64>,
+    /// Seconds over which the iteration limit is averaged so colors don't flicker
+    #[arg(long)]
+    palette_smoothing: Option<f64>,
+}
+
+impl AnimationArgs {
+    fn load(&self) -> Result<Animation, Box<dyn Error>> {
+        let mut animation: Animation = fs::read_to_string(&self.keyframes)?.parse()?;
+        if let Some(frame_rate) = self.frame_rate {
+            animation.frame_rate = frame_rate;
+        }
+        if let Some(motion_blur) = self.motion_blur {
+            animation.motion_blur = motion_blur;
+        }
+        if let Some(shutter_angle) = self.shutter_angle {
+            animation.shutter_angle = shutter_angle;
+        }
+        if let Some(palette_smoothing) = self.palette_smoothing {
+            animation.palette_smoothing = palette_smoothing;
+        }
+        animation.validate()?;
+        Ok(animation)
+    }
+}
+
+#[derive(Debug, Args)]
+struct AnimateArgs {
+    #[command(flatten)]
+    animation: AnimationArgs,
+    /// Directory to write the frames to
+    #[arg(long, default_value = "frames")]
+    output: PathBuf,
+    #[arg(long, default_value_t = 1920)]
+    width: u32,
+    #[arg(long, default_value_t = 1080)]
+    height: u32,
+    /// Render on the CPU even if there is a GPU
+    #[arg(long)]
+    cpu: bool,
+}
+
+#[derive(Debug, Args)]
+struct VideoArgs {
+    #[command(flatten)]
+    animation: AnimationArgs,
+    /// File to write the video to, `-` for standard output
+    #[arg(long, default_value = "-")]
+    output: PathBuf,
+    /// `y4m`, or `yuv420` or `rgba` for bare frames
+    #[arg(long, default_value = "y4m")]
+    format: VideoFormat,
+    #[arg(long, default_value_t = 1920)]
+    width: u32,
+    #[arg(long, default_value_t = 1080)]
+    height: u32,
+    /// Render on the CPU even if there is a GPU
+    #[arg(long)]
+    cpu: bool,
+}
+
+#[derive(Debug, Args)]
+struct ExpMapArgs {
+    /// Point to zoom in on, as `re,im`
+    #[arg(long, value_parser = parse_complex, allow_hyphen_values = true)]
+    center: Vec2,
+    #[arg(long, default_value_t = 1.0)]
+    from_zoom: f64,
+    #[arg(long)]
+    to_zoom: f64,
+    #[command(flatten)]
+    settings: SettingsArgs,
+    /// Length of the video in seconds
+    #[arg(long, default_value_t = 10.0)]
+    duration: f64,
+    #[arg(long, default_value_t = Animation::DEFAULT_FRAME_RATE)]
+    frame_rate: f64,
+    /// File to write the video to, `-` for standard output
+    #[arg(long, default_value = "-")]
+    output: PathBuf,
+    /// `y4m`, or `yuv420` or `rgba` for bare frames
+    #[arg(long, default_value = "y4m")]
+    format: VideoFormat,
+    #[arg(long, default_value_t = 1920)]
+    width: u32,
+    #[arg(long, default_value_t = 1080)]
+    height: u32,
+    /// Also save the exponential map as a PNG, with the view at `--to-zoom` as its metadata
+    #[arg(long, value_name = "PNG")]
+    save_map: Option<PathBuf>,
+    /// Render on the CPU even if there is a GPU
+    #[arg(long)]
+    cpu: bool,
+}
+
+#[derive(Debug, Args)]
+struct InfoArgs {
+    /// The point as `re,im`
+    #[arg(value_parser = parse_complex, allow_hyphen_values = true)]
+    point: Vec2,
+    #[arg(long, default_value_t = 1000)]
+    iterations: u32,
+    /// Number of orbit points to print
+    #[arg(long, default_value_t = 16)]
+    orbit: usize,
+}
+
+#[derive(Debug, Args)]
+struct BenchArgs {
+    #[arg(long, default_value_t = 1280)]
+    width: u32,
+    #[arg(long, default_value_t = 720)]
+    height: u32,
+    /// Renders of every location
+    #[arg(long, default_value_t = 5)]
+    frames: u32,
+    /// Render on the CPU even if there is a GPU
+    #[arg(long)]
+    cpu: bool,
+}
+
+#[derive(Debug, Args)]
+struct BookmarksArgs {
+    /// Bookmark file to use instead of the one in the config directory
+    #[arg(long)]
+    file: Option<PathBuf>,
+    #[command(subcommand)]
+    command: BookmarksCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum BookmarksCommand {
+    /// Print the bookmarks, only those that match a search if one is given
+    List { search: Vec<String> },
+    /// Save a location as a bookmark, replacing any with the same name
+    Add {
+        name: String,
+        #[command(flatten)]
+        view: ViewArgs,
+        #[arg(long, default_value = "")]
+        notes: String,
+        /// Don't render a thumbnail of the location
+        #[arg(long)]
+        no_thumbnail: bool,
+    },
+    /// Delete a bookmark
+    Remove { name: String },
+}
+
+#[derive(Debug, Args)]
+struct BindingsArgs {
+    /// Bindings file to use instead of the one in the config directory
+    #[arg(long)]
+    file: Option<PathBuf>,
+}
+
+/// Locations rendered by `bench`, from cheap to expensive.
+const BENCH_VIEWS: [(&str, [f64; 2], f64); 4] = [
+    ("full set", [-0.5, 0.0], 1.0),
+    ("seahorse valley", [-0.7453, 0.1127], 100.0),
+    ("elephant valley", [0.285, 0.0135], 100.0),
+    ("minibrot", [-1.78602, 0.0], 2000.0),
+];
+
+fn parse_complex(s: &str) -> Result<Vec2, String> {
+    let (re, im) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected `re,im`, got `{s}`"))?;
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<f64>()
+            .map_err(|err| format!("`{part}`: {err}"))
+    };
+    Ok(Vec2::new(parse(re)?, parse(im)?))
+}
+
+fn parse_antialiasing(s: &str) -> Result<Antialiasing, String> {
+    if s == "off" {
+        return Ok(Antialiasing::Off);
+    }
+    let (mode, n) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected `off` or `mode:n`, got `{s}`"))?;
+    let n = n.parse().map_err(|err| format!("`{n}`: {err}"))?;
+    match mode {
+        "supersample" => Ok(Antialiasing::Supersample(n)),
+        "jitter" => Ok(Antialiasing::Jitter(n)),
+        "adaptive" => Ok(Antialiasing::Adaptive(n)),
+        _ => Err(format!("unknown antialiasing mode `{mode}`")),
+    }
+}
+
+fn renderer(cpu: bool) -> Renderer {
+    if cpu {
+        Renderer::Cpu
+    } else {
+        pollster::block_on(Renderer::new())
+    }
+}
+
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let Some(command) = cli.command else {
+        wgpu_mandelbrot::run();
+        return Ok(());
+    };
+    if let Command::Explore { view, settings } = command {
+        let (view, metadata) = view.load()?;
+        wgpu_mandelbrot::explore(Some(view), settings.settings(metadata.as_ref()));
+        return Ok(());
+    }
+    pretty_env_logger::init();
+    match command {
+        Command::Explore { .. } => unreachable!(),
+        Command::Render(args) => render(args),
+        Command::Recolor(args) => recolor(args),
+        Command::Batch(args) => batch(args),
+        Command::Animate(args) => animate(args),
+        Command::Video(args) => video(args),
+        Command::ExpMap(args) => exp_map(args),
+        Command::Info(args) => {
+            info(args);
+            Ok(())
+        }
+        Command::Bench(args) => bench(args),
+        Command::Bookmarks(args) => bookmarks(args),
+        Command::Bindings(args) => bindings(args),
+    }
+}
+
+fn render(args: RenderArgs) -> Result<(), Box<dyn Error>> {
+    let (view, metadata) = args.view.load()?;
+    let job = Job {
+        width: args.width,
+        height: args.height,
+        depth: args.depth,
+        ..Job::new(
+            view,
+            args.settings.still_settings(metadata.as_ref())?,
+            args.output,
+        )
+    };
+    let start = Instant::now();
+    job.render(&renderer(args.cpu))?;
+    println!("wrote {} in {:.2?}", job.output.display(), start.elapsed());
+    Ok(())
+}
+
+fn recolor(args: RecolorArgs) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let data = PixelData::load_raw(&args.input)
+        .map_err(|err| format!("can't read {}: {err}", args.input.display()))?;
+    let palette = args.palette.unwrap_or_default();
+    let extension = args
+        .output
+        .extension()
+        .and_then(|it| it.to_str())
+        .map(str::to_ascii_lowercase);
+    match (extension.as_deref(), args.depth) {
+        (Some("png"), 8) => data.to_image(&palette).save_png(&args.output, None)?,
+        (Some("png"), 16) => data.save_png16(&palette, &args.output, None)?,
+        (Some("png"), depth) => {
+            return Err(format!("can't write {depth} bit PNGs, only 8 or 16").into())
+        }
+        (Some("exr"), _) => data.save_exr(&palette, &args.output)?,
+        _ => {
+            return Err(format!(
+                "don't know how to write {}, use .png or .exr",
+                args.output.display()
+            )
+            .into())
+        }
+    }
+    println!("wrote {} in {:.2?}", args.output.display(), start.elapsed());
+    Ok(())
+}
+
+fn batch(args: BatchArgs) -> Result<(), Box<dyn Error>> {
+    let batch = Batch::load(&args.jobs)?;
+    let renderer = renderer(args.cpu);
+    let count = batch.jobs.len();
+    let start = Instant::now();
+    let reports = batch.run(&renderer, |report| match &report.result {
+        Ok(()) => println!(
+            "[{}/{count}] wrote {} in {:.2?}",
+            report.index + 1,
+            report.output.display(),
+            report.elapsed
+        ),
+        Err(err) => eprintln!(
+            "[{}/{count}] {} failed after {:.2?}: {err}",
+            report.index + 1,
+            report.output.display(),
+            report.elapsed
+        ),
+    });
+    let failed = reports.iter().filter(|it| it.result.is_err()).count();
+    println!(
+        "rendered {} of {count} jobs in {:.2?}",
+        count - failed,
+        start.elapsed()
+    );
+    if failed > 0 {
+        return Err(format!("{failed} of {count} jobs failed").into());
+    }
+    Ok(())
+}
+
+fn animate(args: AnimateArgs) -> Result<(), Box<dyn Error>> {
+    let animation = args.animation.load()?;
+    let renderer = renderer(args.cpu);
+    let count = animation.frame_count();
+    let start = Instant::now();
+    animation.render_frames(&renderer, args.width, args.height, &args.output, |report| {
+        if let Ok(()) = report.result {
+            println!(
+                "[{}/{count}] wrote {} in {:.2?}",
+                report.index + 1,
+                report.output.display(),
+                report.elapsed
+            );
+        }
+    })?;
+    println!("rendered {count} frames in {:.2?}", start.elapsed());
+    Ok(())
+}
+
+fn video(args: VideoArgs) -> Result<(), Box<dyn Error>> {
+    let animation = args.animation.load()?;
+    let mut video = VideoWriter::new(
+        BufWriter::new(create_output(&args.output)?),
+        args.format,
+        args.width,
+        args.height,
+        animation.frame_rate,
+    )?;
+    let renderer = renderer(args.cpu);
+    let count = animation.frame_count();
+    let start = Instant::now();
+    // the video may be going to standard output, so progress goes to standard error
+    animation.render_video(
+        &renderer,
+        &mut video,
+        args.width,
+        args.height,
+        |index, elapsed| eprintln!("[{}/{count}] rendered in {elapsed:.2?}", index + 1),
+    )?;
+    video.finish()?;
+    eprintln!("rendered {count} frames in {:.2?}", start.elapsed());
+    Ok(())
+}
+
+/// Standard output for `-` and otherwise a new file at `path`.
+fn create_output(path: &Path) -> io::Result<Box<dyn Write>> {
+    Ok(if path.as_os_str() == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(File::create(path)?)
+    })
+}
+
+fn exp_map(args: ExpMapArgs) -> Result<(), Box<dyn Error>> {
+    let (width, height) = (args.width, args.height);
+    let map = ExpMap {
+        settings: args.settings.still_settings(None)?,
+        ..ExpMap::for_zoom(args.center, args.from_zoom, args.to_zoom, width, height)
+    };
+    let renderer = renderer(args.cpu);
+    let start = Instant::now();
+    let image = map.render(&renderer)?;
+    eprintln!(
+        "rendered a {}x{} map in {:.2?}",
+        map.width,
+        map.height,
+        start.elapsed()
+    );
+    if let Some(path) = &args.save_map {
+        image.save_png(path, Some(&map.metadata(args.to_zoom)))?;
+    }
+
+    let mut video = VideoWriter::new(
+        BufWriter::new(create_output(&args.output)?),
+        args.format,
+        width,
+        height,
+        args.frame_rate,
+    )?;
+    let count = (args.duration * args.frame_rate).floor() as u32 + 1;
+    let zoom_ratio = args.to_zoom / args.from_zoom;
+    let start = Instant::now();
+    for index in 0..count {
+        let progress = index as f64 / (count - 1).max(1) as f64;
+        let view = View::new(args.center, args.from_zoom * zoom_ratio.powf(progress));
+        video.write_frame(&map.reproject(&image, &view, width, height))?;
+    }
+    video.finish()?;
+    eprintln!("reprojected {count} frames in {:.2?}", start.elapsed());
+    Ok(())
+}
+
+fn info(args: InfoArgs) {
+    let orbit = Orbit::new(args.point, args.iterations);
+    println!("c = {}{:+}i", args.point.x, args.point.y);
+    match orbit.escape_time {
+        Some(i) => println!("escape time: {i} iterations"),
+        None => println!("escape time: none within {} iterations", args.iterations),
+    }
+    match orbit.period {
+        Some(p) => println!("period: {p}"),
+        None => println!("period: none found"),
+    }
+    println!("orbit:");
+    for (i, z) in orbit.points.iter().take(args.orbit).enumerate() {
+        println!("{i:>6}: {}{:+}i", z.x, z.y);
+    }
+    if orbit.points.len() > args.orbit {
+        println!("   ...  {} more", orbit.points.len() - args.orbit);
+    }
+}
+
+fn bench(args: BenchArgs) -> Result<(), Box<dyn Error>> {
+    let renderer = renderer(args.cpu);
+    match &renderer {
+        Renderer::Gpu(gpu) => println!("rendering on {}", gpu.adapter_info().name),
+        Renderer::Cpu => println!("rendering on the CPU"),
+    }
+    let settings = Settings::default();
+    let pixels = args.width as f64 * args.height as f64 * args.frames as f64;
+    for (name, [x, y], zoom) in BENCH_VIEWS {
+        let view = View::new(Vec2::new(x, y), zoom);
+        // the first render includes pipeline warm up and isn't counted
+        renderer.render(&view, &settings, args.width, args.height)?;
+        let start = Instant::now();
+        for _ in 0..args.frames {
+            renderer.render(&view, &settings, args.width, args.height)?;
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        println!(
+            "{name:<16} {:>9.2} ms/frame {:>9.1} Mpx/s",
+            elapsed * 1000.0 / args.frames as f64,
+            pixels / elapsed / 1e6
+        );
+    }
+    Ok(())
+}
+
+/// The bookmark file at `path`, or else the one in the config directory.
+fn bookmarks_path(path: Option<&Path>) -> Result<PathBuf, Box<dyn Error>> {
+    match path {
+        Some(path) => Ok(path.to_path_buf()),
+        None => Ok(Bookmarks::default_path().ok_or("there is no config directory for bookmarks")?),
+    }
+}
+
+fn load_bookmarks(path: Option<&Path>) -> Result<Bookmarks, Box<dyn Error>> {
+    Ok(Bookmarks::load(bookmarks_path(path)?)?)
+}
+
+fn bookmarks(args: BookmarksArgs) -> Result<(), Box<dyn Error>> {
+    let path = bookmarks_path(args.file.as_deref())?;
+    let mut bookmarks = Bookmarks::load(&path)?;
+    match args.command {
+        BookmarksCommand::List { search } => {
+            let query = search.join(" ");
+            for bookmark in bookmarks.search(&query) {
+                let View {
+                    center,
+                    zoom,
+                    rotation,
+                } = bookmark.view;
+                println!(
+                    "{:<24} {}{:+}i  zoom {zoom:.3e}  rotation {rotation:.3}",
+                    bookmark.name, center.x, center.y
+                );
+                if !bookmark.notes.is_empty() {
+                    println!("{:<24} {}", "", bookmark.notes);
+                }
+            }
+        }
+        BookmarksCommand::Add {
+            name,
+            view,
+            notes,
+            no_thumbnail,
+        } => {
+            let (view, metadata) = view.load()?;
+            let mut bookmark = Bookmark::new(name, view).with_notes(notes);
+            if !no_thumbnail {
+                let settings = metadata.map_or_else(Settings::default, |it| Settings {
+                    palette: it.palette,
+                    ..Settings::default()
+                });
+                Bookmarks::save_thumbnail(&mut bookmark, &path, &settings)?;
+            }
+            let name = bookmark.name.clone();
+            if bookmarks.add(bookmark).is_some() {
+                println!("replaced {name}");
+            } else {
+                println!("added {name}");
+            }
+            bookmarks.save(&path)?;
+        }
+        BookmarksCommand::Remove { name } => {
+            bookmarks
+                .remove(&name)
+                .ok_or_else(|| format!("there is no bookmark named `{name}`"))?;
+            bookmarks.save(&path)?;
+            println!("removed {name}");
+        }
+    }
+    Ok(())
+}
+
+fn bindings(args: BindingsArgs) -> Result<(), Box<dyn Error>> {
+    let bindings = match args.file.or_else(Bindings::default_path) {
+        Some(path) => Bindings::load(path)?,
+        None => Bindings::default(),
+    };
+    for (chord, action) in bindings.iter() {
+        println!("{:<24} {action}", chord.to_string());
+    }
+    println!();
+    println!("pan step        {}", bindings.pan_step);
+    println!("zoom step       {}", bindings.zoom_step);
+    println!("fine zoom step  {}", bindings.fine_zoom_step);
+    println!("rotation step   {}°", bindings.rotation_step.to_degrees());
+    println!("wheel zoom      {}", bindings.wheel_zoom);
+    println!("snap angle      {}°", bindings.snap_angle.to_degrees());
+    println!("rotation pivot  {:?}", bindings.rotation_pivot);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu_mandelbrot::image::Image;
+
+    #[test]
+    fn test_parse_view() {
+        let cli = Cli::parse_from([
+            "mandelbrot",
+            "render",
+            "--center",
+            "-0.75,0.1",
+            "--zoom",
+            "30",
+            "--rotation",
+            "-1.5",
+            "--antialiasing",
+            "supersample:4",
+            "out.png",
+        ]);
+        let Some(Command::Render(args)) = cli.command else {
+            panic!("expected render, got {cli:?}");
+        };
+        let (view, metadata) = args.view.load().unwrap();
+        assert_eq!(metadata, None);
+        assert_eq!(
+            view,
+            View {
+                center: Vec2::new(-0.75, 0.1),
+                zoom: 30.0,
+                rotation: -1.5,
+            }
+        );
+        assert_eq!(
+            args.settings.settings(None),
+            Settings {
+                antialiasing: Antialiasing::Supersample(4),
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_settings_from_metadata() {
+        let metadata = Metadata::new(View::default(), &Settings::default(), 300, 200);
+        let cli = Cli::parse_from(["mandelbrot", "explore", "--iterations", "50"]);
+        let Some(Command::Explore { settings, .. }) = cli.command else {
+            panic!("expected explore, got {cli:?}");
+        };
+        let settings = settings.settings(Some(&metadata));
+        assert_eq!(settings.max_iterations, Some(50));
+        assert_eq!(settings.palette, metadata.palette);
+    }
+
+    #[test]
+    fn test_still_images_only_supersample() {
+        for (antialiasing, ok) in [
+            ("supersample:3", true),
+            ("jitter:8", false),
+            ("adaptive:4", false),
+        ] {
+            let cli = Cli::parse_from([
+                "mandelbrot",
+                "render",
+                "--antialiasing",
+                antialiasing,
+                "out.png",
+            ]);
+            let Some(Command::Render(args)) = cli.command else {
+                panic!("expected render, got {cli:?}");
+            };
+            assert_eq!(
+                args.settings.still_settings(None).is_ok(),
+                ok,
+                "{antialiasing}"
+            );
+        }
+
+        let cli = Cli::parse_from([
+            "mandelbrot",
+            "exp-map",
+            "--center",
+            "-0.75,0.1",
+            "--to-zoom",
+            "1000",
+            "--antialiasing",
+            "adaptive:2",
+        ]);
+        let Some(Command::ExpMap(args)) = cli.command else {
+            panic!("expected exp-map, got {cli:?}");
+        };
+        assert!(exp_map(args).is_err());
+        // the window takes every mode
+        let cli = Cli::parse_from(["mandelbrot", "explore", "--antialiasing", "jitter:8"]);
+        let Some(Command::Explore { settings, .. }) = cli.command else {
+            panic!("expected explore, got {cli:?}");
+        };
+        assert_eq!(
+            settings.settings(None).antialiasing,
+            Antialiasing::Jitter(8)
+        );
+    }
+
+    #[test]
+    fn test_animation_overrides_are_checked() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-keyframes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let keyframes = dir.join("keyframes.toml");
+        fs::write(&keyframes, "[[keyframe]]\ntime = 0\n").unwrap();
+        let load = |option: &str| {
+            let cli =
+                Cli::parse_from(["mandelbrot", "animate", keyframes.to_str().unwrap(), option]);
+            let Some(Command::Animate(args)) = cli.command else {
+                panic!("expected animate, got {cli:?}");
+            };
+            args.animation.load()
+        };
+        assert_eq!(load("--frame-rate=24").unwrap().frame_rate, 24.0);
+        assert!(load("--frame-rate=0").is_err());
+        assert!(load("--shutter-angle=720").is_err());
+        assert!(load("--palette-smoothing=-1").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_antialiasing() {
+        assert_eq!(parse_antialiasing("off"), Ok(Antialiasing::Off));
+        assert_eq!(
+            parse_antialiasing("jitter:16"),
+            Ok(Antialiasing::Jitter(16))
+        );
+        assert!(parse_antialiasing("blur:2").is_err());
+        assert!(parse_complex("1").is_err());
+    }
+
+    #[test]
+    fn test_recolor_raw_data() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-recolor-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (raw, png) = (dir.join("set.raw"), dir.join("set.png"));
+        let data =
+            wgpu_mandelbrot::cpu::render_data(&View::default(), &Settings::default(), 30, 20);
+        data.save_raw(&raw).unwrap();
+
+        let cli = Cli::parse_from([
+            "mandelbrot",
+            "recolor",
+            "--palette",
+            "frequency 3",
+            raw.to_str().unwrap(),
+            png.to_str().unwrap(),
+        ]);
+        let Some(Command::Recolor(args)) = cli.command else {
+            panic!("expected recolor, got {cli:?}");
+        };
+        let palette = args.palette.unwrap();
+        recolor(args).unwrap();
+        assert_eq!(Image::load_png(&png).unwrap(), data.to_image(&palette));
+        assert_ne!(data.to_image(&palette), data.to_image(&Palette::default()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_bookmarks() {
+        let cli = Cli::parse_from(["mandelbrot", "bookmarks", "list", "seahorse", "valley"]);
+        let Some(Command::Bookmarks(BookmarksArgs {
+            file: None,
+            command: BookmarksCommand::List { search },
+        })) = cli.command
+        else {
+            panic!("expected bookmarks list, got {cli:?}");
+        };
+        assert_eq!(search, ["seahorse", "valley"]);
+
+        let from_both = [
+            "mandelbrot",
+            "explore",
+            "--from",
+            "a.png",
+            "--bookmark",
+            "b",
+        ];
+        assert!(Cli::try_parse_from(from_both).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! A CPU implementation of `shader.wgsl`.
+//!
+//! Every pixel goes through the same steps as in the fragment shader, in `f32` like on the GPU, so
+//! the images only differ where the GPU rounds differently. That makes this the reference that GPU
+//! output is tested against, and a way to render on machines without any adapter at all.
+
+use std::thread;
+
+use kurbo::Vec2;
+
+use crate::{
+    data::{PixelData, Sample},
+    image::Image,
+    settings::Settings,
+    view::View,
+    Globals,
+};
+
+/// Render `view` into a `width` by `height` image with `settings`.
+pub fn render(view: &View, settings: &Settings, width: u32, height: u32) -> Image {
+    render_globals(
+        &Globals::for_view(view, settings, width, height),
+        width,
+        height,
+    )
+}
+
+/// Render with the same uniforms that are handed to the shader, spreading rows over all cores.
+pub(crate) fn render_globals(globals: &Globals, width: u32, height: u32) -> Image {
+    let mut image = Image::new(width, height);
+    for_each_row(&mut image.pixels, width as usize * 4, |y, row| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let center = [x as f32 + 0.5, y as f32 + 0.5];
+            pixel.copy_from_slice(&to_unorm(shade(globals, center)));
+        }
+    });
+    image
+}
+
+/// Compute the [`PixelData`] of `view` at `width` by `height`, iterating every pixel center the
+/// same way the shader does. The palette and antialiasing of `settings` don't matter here.
+pub fn render_data(view: &View, settings: &Settings, width: u32, height: u32) -> PixelData {
+    let viewport = Vec2::new(width as f64, height as f64);
+    let view_to_complex = view.pixel_to_complex(viewport);
+    let globals = Globals::for_view(view, settings, width, height);
+    let max_i = globals.max_iterations();
+    let mut samples = vec![Sample::default(); width as usize * height as usize];
+    for_each_row(&mut samples, width as usize, |y, row| {
+        for (x, sample) in row.iter_mut().enumerate() {
+            let c = pixel_to_complex(&globals, [x as f32 + 0.5, y as f32 + 0.5]);
+            *sample = iterate(&globals, c, max_i).sample(max_i);
+        }
+    });
+    PixelData {
+        width,
+        height,
+        max_iterations: max_i,
+        pixel_size: view_to_complex.determinant().abs().sqrt(),
+        samples,
+    }
+}
+
+/// Call `f` with the index and contents of every `row_length` long row of `buffer`, on as many
+/// threads as there are cores.
+pub(crate) fn for_each_row<T: Send>(
+    buffer: &mut [T],
+    row_length: usize,
+    f: impl Fn(usize, &mut [T]) + Sync,
+) {
+    if row_length == 0 {
+        return;
+    }
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    // Rows are dealt out in turn rather than in bands so that every thread gets a similar share of
+    // the expensive rows through the set.
+    let mut shares: Vec<Vec<(usize, &mut [T])>> = (0..threads).map(|_| Vec::new()).collect();
+    for (y, row) in buffer.chunks_exact_mut(row_length).enumerate() {
+        shares[y % threads].push((y, row));
+    }
+    let f = &f;
+    thread::scope(|scope| {
+        for share in shares {
+            scope.spawn(move || {
+                for (y, row) in share {
+                    f(y, row);
+                }
+            });
+        }
+    });
+}
+
+/// Apply `transform` the way `transform_point` in the shader does.
+fn transform_point(transform: &[f32; 6], [x, y]: [f32; 2]) -> [f32; 2] {
+    let [a, b, c, d, e, f] = *transform;
+    [a * x + c * y + e, b * x + d * y + f]
+}
+
+/// The point shown at `pixel`, like `pixel_to_complex` in the shader.
+fn pixel_to_complex(globals: &Globals, pixel: [f32; 2]) -> [f32; 2] {
+    let [log_radius, angle, step, _] = globals.exp_map;
+    if step == 0.0 {
+        return transform_point(&globals.transform, pixel);
+    }
+    let radius = (log_radius - pixel[1] * step).exp();
+    let angle = angle + pixel[0] * step;
+    let [x, y] = globals.exp_map_center;
+    [x + radius * angle.cos(), y + radius * angle.sin()]
+}
+
+/// Where the iteration of a point stopped.
+struct Orbit {
+    /// Number of iterations, `max_i` for points that are considered inside
+    iterations: u32,
+    z: [f32; 2],
+    /// Derivative of `z` with respect to `c`
+    dz: [f32; 2],
+}
+
+impl Orbit {
+    fn sample(&self, max_i: u32) -> Sample {
+        let [x, y] = self.z;
+        if self.iterations >= max_i {
+            return Sample {
+                smooth: max_i as f32,
+                distance: 0.0,
+                z: self.z,
+            };
+        }
+        let modulus = x.hypot(y);
+        let log_modulus = modulus.ln();
+        Sample {
+            smooth: self.iterations as f32 + 1.0 - (log_modulus / std::f32::consts::LN_2).log2(),
+            distance: 0.5 * modulus * log_modulus / self.dz[0].hypot(self.dz[1]),
+            z: self.z,
+        }
+    }
+}
+
+/// Iterate the point `c` the way `escape_time` in the shader does.
+fn iterate(globals: &Globals, [cx, cy]: [f32; 2], max_i: u32) -> Orbit {
+    let (mut x, mut y) = (0.0f32, 0.0f32);
+    let (mut dx, mut dy) = (0.0f32, 0.0f32);
+    let mut i = 0;
+    let budget = ((max_i as f32 * globals.iteration_scale) as u32).max(1);
+    let epsilon = 1e-3;
+    loop {
+        if i >= budget {
+            i = max_i;
+            break;
+        }
+        if x * x + y * y > 4.0 {
+            break;
+        }
+        let x_new = x * x - y * y + cx;
+        let y_new = 2.0 * x * y + cy;
+        if (x_new - x).hypot(y_new - y) < epsilon {
+            i = max_i;
+            break;
+        }
+        // dz' = 2 z dz + 1
+        (dx, dy) = (2.0 * (x * dx - y * dy) + 1.0, 2.0 * (x * dy + y * dx));
+        (x, y) = (x_new, y_new);
+        i += 1;
+    }
+    Orbit {
+        iterations: i,
+        z: [x, y],
+        dz: [dx, dy],
+    }
+}
+
+/// Normalized iteration count of the point `c`, 1 for points that are considered inside.
+fn escape_time(globals: &Globals, c: [f32; 2], max_i: u32) -> f32 {
+    iterate(globals, c, max_i).iterations as f32 / max_i as f32
+}
+
+/// Average color of the samples in the pixel centered at `pixel`, like `shade` in the shader.
+fn shade(globals: &Globals, pixel: [f32; 2]) -> [f32; 4] {
+    let max_i = globals.max_iterations();
+    let samples = globals.samples;
+    let palette = globals.palette();
+    let mut color = [0.0f32; 3];
+    let mut t_sum = 0.0;
+    for y in 0..samples {
+        for x in 0..samples {
+            let offset = [x, y].map(|it| (it as f32 + 0.5) / samples as f32 - 0.5);
+            let point = [
+                pixel[0] + offset[0] + globals.jitter[0],
+                pixel[1] + offset[1] + globals.jitter[1],
+            ];
+            let t = escape_time(globals, pixel_to_complex(globals, point), max_i);
+            for (sum, channel) in color.iter_mut().zip(palette.color(t)) {
+                *sum += channel;
+            }
+            t_sum += t;
+        }
+    }
+    let count = (samples * samples) as f32;
+    let alpha = if globals.iteration_alpha != 0 {
+        t_sum / count
+    } else {
+        1.0
+    };
+    let [r, g, b] = color.map(|it| it / count);
+    [r, g, b, alpha]
+}
+
+/// Convert to 8 bits per channel the way a `Rgba8Unorm` render target does.
+fn to_unorm(color: [f32; 4]) -> [u8; 4] {
+    color.map(|it| (it.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_time() {
+        let globals = Globals::new();
+        // the origin converges right away, 1 escapes on the third iteration
+        assert_eq!(escape_time(&globals, [0.0, 0.0], 8), 1.0);
+        assert_eq!(escape_time(&globals, [1.0, 0.0], 8), 3.0 / 8.0);
+        let coarse = Globals {
+            iteration_scale: 0.25,
+            ..Globals::new()
+        };
+        // with only two iterations to spend 1 looks like it is inside
+        assert_eq!(escape_time(&coarse, [1.0, 0.0], 8), 1.0);
+    }
+
+    #[test]
+    fn test_render_matches_across_threads() {
+        let (width, height) = (61, 37);
+        let globals = Globals::for_view(&View::default(), &Settings::default(), width, height);
+        let image = render_globals(&globals, width, height);
+        assert_eq!(image.pixels.len(), 61 * 37 * 4);
+        // every row ends up where it belongs no matter which thread rendered it
+        for y in 0..height {
+            for x in 0..width {
+                let center = [x as f32 + 0.5, y as f32 + 0.5];
+                assert_eq!(image.pixel(x, y), to_unorm(shade(&globals, center)));
+            }
+        }
+        assert!(image.pixels.chunks_exact(4).all(|pixel| pixel[3] == 255));
+        assert_ne!(image.pixel(0, 0), image.pixel(30, 18));
+    }
+}
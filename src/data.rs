@@ -0,0 +1,258 @@
+//! Per-pixel iteration data, for exporting at high bit depths and recoloring without iterating
+//! again.
+//!
+//! The `recolor` command reads a raw file back and colors it with another palette.
+//!
+//! # Raw file format
+//!
+//! A raw file is a header followed by one record per pixel, rows from top to bottom and pixels
+//! from left to right. All numbers are little endian.
+//!
+//! | Offset | Type      | Contents                                               |
+//! |--------|-----------|--------------------------------------------------------|
+//! | 0      | `[u8; 8]` | The magic bytes `MBROTRAW`                             |
+//! | 8      | `u32`     | Format version, currently 1                            |
+//! | 12     | `u32`     | Width in pixels                                        |
+//! | 16     | `u32`     | Height in pixels                                       |
+//! | 20     | `u32`     | Iteration limit                                        |
+//! | 24     | `f64`     | Width of a pixel in the complex plane                  |
+//! | 32     | records   | `width * height` records of 16 bytes                   |
+//!
+//! Every record holds four `f32`s:
+//!
+//! 1. The smooth iteration count. Points that are considered inside the set have the iteration
+//!    limit here, escaped points a continuous count that lies within one of the whole number of
+//!    iterations they took.
+//! 2. The distance estimate to the boundary of the set in the complex plane, 0 inside of it.
+//!    Divide by the pixel width from the header to get a distance in pixels.
+//! 3. The real part of the last `z`.
+//! 4. The imaginary part of the last `z`.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{image::Image, metadata::Metadata, palette::Palette};
+
+const MAGIC: &[u8; 8] = b"MBROTRAW";
+const VERSION: u32 = 1;
+
+/// What is known about a pixel after iterating it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sample {
+    /// Continuous iteration count, the iteration limit for points inside of the set
+    pub smooth: f32,
+    /// Estimated distance to the boundary of the set in the complex plane
+    pub distance: f32,
+    /// The last value of `z`
+    pub z: [f32; 2],
+}
+
+/// A [`Sample`] for every pixel of an image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PixelData {
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u32,
+    /// Width of a pixel in the complex plane
+    pub pixel_size: f64,
+    /// Rows from top to bottom without any padding
+    pub samples: Vec<Sample>,
+}
+
+impl PixelData {
+    /// Write the data in the raw format described in the [module documentation](self).
+    pub fn write_raw(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        for value in [VERSION, self.width, self.height, self.max_iterations] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&self.pixel_size.to_le_bytes())?;
+        for sample in &self.samples {
+            for value in [sample.smooth, sample.distance, sample.z[0], sample.z[1]] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Read data written by [`PixelData::write_raw`].
+    pub fn read_raw(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a raw Mandelbrot data file"));
+        }
+        let mut read_u32 = || -> io::Result<u32> {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        };
+        let version = read_u32()?;
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported raw data version {version}")));
+        }
+        let (width, height, max_iterations) = (read_u32()?, read_u32()?, read_u32()?);
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        let pixel_size = f64::from_le_bytes(bytes);
+
+        let mut records = Vec::new();
+        reader.read_to_end(&mut records)?;
+        if records.len() != width as usize * height as usize * 16 {
+            return Err(invalid(
+                "the number of records doesn't match the image size",
+            ));
+        }
+        let samples = records
+            .chunks_exact(16)
+            .map(|record| {
+                let [smooth, distance, x, y] = std::array::from_fn(|i| {
+                    f32::from_le_bytes(record[i * 4..i * 4 + 4].try_into().unwrap())
+                });
+                Sample {
+                    smooth,
+                    distance,
+                    z: [x, y],
+                }
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            max_iterations,
+            pixel_size,
+            samples,
+        })
+    }
+
+    pub fn save_raw(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_raw(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load_raw(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_raw(BufReader::new(File::open(path)?))
+    }
+
+    /// The color of every pixel with `palette`, rows from top to bottom.
+    pub fn colors(&self, palette: &Palette) -> impl Iterator<Item = [f32; 3]> + '_ {
+        let palette = *palette;
+        let max_iterations = self.max_iterations as f32;
+        self.samples
+            .iter()
+            .map(move |sample| palette.color(sample.smooth / max_iterations))
+    }
+
+    /// Color the data with `palette` into an 8-bit image.
+    pub fn to_image(&self, palette: &Palette) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for (pixel, color) in image.pixels.chunks_exact_mut(4).zip(self.colors(palette)) {
+            let [r, g, b] = color.map(|it| (it.clamp(0.0, 1.0) * 255.0).round() as u8);
+            pixel.copy_from_slice(&[r, g, b, 255]);
+        }
+        image
+    }
+
+    /// Color the data with `palette` and encode it as a 16-bit RGB PNG into `writer`, with the
+    /// view it shows if there is `metadata`.
+    pub fn write_png16(
+        &self,
+        palette: &Palette,
+        writer: impl Write,
+        metadata: Option<&Metadata>,
+    ) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        if let Some(metadata) = metadata {
+            metadata.add_to_png(&mut encoder)?;
+        }
+        let data: Vec<u8> = self
+            .colors(palette)
+            .flatten()
+            // PNG stores 16-bit samples big endian
+            .flat_map(|it| ((it.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes())
+            .collect();
+        encoder.write_header()?.write_image_data(&data)
+    }
+
+    pub fn save_png16(
+        &self,
+        palette: &Palette,
+        path: impl AsRef<Path>,
+        metadata: Option<&Metadata>,
+    ) -> Result<(), png::EncodingError> {
+        self.write_png16(palette, BufWriter::new(File::create(path)?), metadata)
+    }
+
+    /// Color the data with `palette` and save it as an OpenEXR image with 32-bit float channels.
+    pub fn save_exr(&self, palette: &Palette, path: impl AsRef<Path>) -> exr::error::UnitResult {
+        let colors: Vec<[f32; 3]> = self.colors(palette).collect();
+        let width = self.width as usize;
+        exr::prelude::write_rgb_file(path, width, self.height as usize, |x, y| {
+            let [r, g, b] = colors[y * width + x];
+            (r, g, b)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cpu, settings::Settings, view::View};
+
+    #[test]
+    fn test_raw_round_trip() {
+        let data = cpu::render_data(&View::default(), &Settings::default(), 12, 7);
+        let mut raw = Vec::new();
+        data.write_raw(&mut raw).unwrap();
+        assert_eq!(raw.len(), 32 + 12 * 7 * 16);
+        assert_eq!(PixelData::read_raw(raw.as_slice()).unwrap(), data);
+
+        raw.truncate(raw.len() - 1);
+        assert!(PixelData::read_raw(raw.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_recolor_matches_render() {
+        let (width, height) = (40, 30);
+        let data = cpu::render_data(&View::default(), &Settings::default(), width, height);
+        let image = cpu::render(&View::default(), &Settings::default(), width, height);
+        let recolored = data.to_image(&Palette::default());
+        // the inside of the set has whole iteration counts so it comes out the same
+        let inside = (15 * width as usize + 20) * 4;
+        assert_eq!(data.samples[inside / 4].smooth, data.max_iterations as f32);
+        assert_eq!(
+            recolored.pixels[inside..inside + 4],
+            image.pixels[inside..inside + 4]
+        );
+    }
+
+    #[test]
+    fn test_png16_matches_image() {
+        let data = cpu::render_data(&View::default(), &Settings::default(), 20, 10);
+        let mut png = Vec::new();
+        data.write_png16(&Palette::default(), &mut png, None)
+            .unwrap();
+        // decoding strips the low byte, which is at most one off from rounding to 8 bits directly
+        let decoded = Image::read_png(png.as_slice()).unwrap();
+        let image = data.to_image(&Palette::default());
+        for (a, b) in decoded.pixels.iter().zip(&image.pixels) {
+            assert!(a.abs_diff(*b) <= 1);
+        }
+    }
+
+    #[test]
+    fn test_smooth_and_distance() {
+        let data = cpu::render_data(&View::default(), &Settings::default(), 60, 40);
+        let top_left = data.samples[0];
+        // escaped points lie between the iteration count they took and the one before it
+        assert!(top_left.smooth > 0.0 && top_left.smooth < 3.0);
+        // the corner at -2 - i is about 1 away from the set
+        let distance = top_left.distance as f64;
+        assert!(distance > 0.2 && distance < 2.0, "{distance}");
+    }
+}
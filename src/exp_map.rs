@@ -0,0 +1,279 @@
+//! Exponential map rendering for long zoom videos.
+//!
+//! An [`ExpMap`] is a log-polar image around the point a video zooms in on, see
+//! [`exp_map_to_complex`]. Every row is a ring a constant factor smaller than the one above it,
+//! so a map a few times taller than wide holds the whole zoom at the detail of a single frame.
+//! Frames are then reprojected from the map instead of being rendered one by one, which is far
+//! cheaper than iterating every pixel of every frame.
+
+use std::f64::consts::TAU;
+
+use kurbo::{Affine, Vec2};
+
+use crate::{
+    cpu,
+    headless::{HeadlessError, Renderer},
+    image::Image,
+    metadata::Metadata,
+    settings::Settings,
+    tiles::{self, Tile},
+    transforms::{complex_to_exp_map, exp_map_to_complex},
+    view::View,
+    Globals,
+};
+
+/// A log-polar image around `center`, from `radius` at the top edge inward.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExpMap {
+    /// The point the map zooms in on
+    pub center: Vec2,
+    /// Distance from `center` of the top edge of the map
+    pub radius: f64,
+    /// Pixels in a full turn around `center`
+    pub width: u32,
+    pub height: u32,
+    /// Only supersampling applies, the iteration limit is the one of the innermost row when it
+    /// isn't set
+    pub settings: Settings,
+    /// Largest side length of a tile, lowered further to what the renderer supports
+    pub tile_size: u32,
+}
+
+impl ExpMap {
+    /// A map `width` pixels around `center` from `outer_radius` inward to at least `inner_radius`.
+    pub fn new(center: Vec2, outer_radius: f64, inner_radius: f64, width: u32) -> Self {
+        let step = TAU / width as f64;
+        let height = ((outer_radius / inner_radius).ln() / step).ceil().max(1.0) as u32;
+        Self {
+            center,
+            radius: outer_radius,
+            width,
+            height,
+            settings: Settings::default(),
+            tile_size: crate::poster::Poster::DEFAULT_TILE_SIZE,
+        }
+    }
+
+    /// The map that every frame of a `frame_width` by `frame_height` zoom into `center` from
+    /// `from_zoom` to `to_zoom` can be reprojected from.
+    ///
+    /// The map reaches from the corners of the first frame to the center pixel of the last one
+    /// and is as wide as it takes for the pixels in the corners of frames to be no larger than
+    /// those of the map.
+    pub fn for_zoom(
+        center: Vec2,
+        from_zoom: f64,
+        to_zoom: f64,
+        frame_width: u32,
+        frame_height: u32,
+    ) -> Self {
+        let viewport = Vec2::new(frame_width as f64, frame_height as f64);
+        let pixel_size = |zoom: f64| {
+            View::new(center, zoom)
+                .pixel_to_complex(viewport)
+                .determinant()
+                .abs()
+                .sqrt()
+        };
+        let half_diagonal = viewport.hypot() / 2.0;
+        let width = (TAU * half_diagonal).ceil() as u32;
+        Self::new(
+            center,
+            pixel_size(from_zoom) * half_diagonal,
+            pixel_size(to_zoom) / 2.0,
+            width,
+        )
+    }
+
+    /// The change in angle and in the log of the radius from one pixel to the next.
+    pub fn step(&self) -> f64 {
+        TAU / self.width as f64
+    }
+
+    /// Distance from `center` of the bottom edge of the map.
+    pub fn inner_radius(&self) -> f64 {
+        self.radius * (-(self.height as f64) * self.step()).exp()
+    }
+
+    /// The point of the complex plane at `pixel` of the map.
+    pub fn pixel_to_complex(&self, pixel: Vec2) -> Vec2 {
+        exp_map_to_complex(self.center, self.radius.ln(), self.step(), pixel)
+    }
+
+    /// The iteration limit of every pixel, the one set in the settings or else the one a view
+    /// with pixels as large as those of the innermost row would have, so the colors of frames
+    /// reprojected from the map don't shift as the zoom goes on.
+    pub fn max_iterations(&self) -> u32 {
+        let max_iterations = self.settings.max_iterations.unwrap_or_else(|| {
+            let pixel_size = self.inner_radius() * self.step();
+            Globals::for_viewport(Affine::scale(pixel_size), 1, 1).max_iterations()
+        });
+        max_iterations.max(1)
+    }
+
+    /// The metadata to save the map with. The map isn't a view of its own, so it stores the view
+    /// at `zoom` around the center, usually the one the zoom ends at, with the palette and
+    /// iteration limit of the map.
+    pub fn metadata(&self, zoom: f64) -> Metadata {
+        Metadata {
+            max_iterations: self.max_iterations(),
+            ..Metadata::new(View::new(self.center, zoom), &self.settings, 1, 1)
+        }
+    }
+
+    /// The uniforms that render `tile` of the map.
+    fn tile_globals(&self, tile: Tile) -> Globals {
+        let step = self.step();
+        let settings = Settings {
+            max_iterations: Some(self.max_iterations()),
+            ..self.settings
+        };
+        Globals {
+            samples: settings.antialiasing.samples(),
+            exp_map_center: [self.center.x as f32, self.center.y as f32],
+            exp_map: [
+                (self.radius.ln() - tile.y as f64 * step) as f32,
+                (tile.x as f64 * step) as f32,
+                step as f32,
+                0.0,
+            ],
+            ..Globals::for_viewport(Affine::IDENTITY, tile.width, tile.height)
+                .with_settings(&settings)
+        }
+    }
+
+    /// Render the whole map, in tiles no larger than `renderer` supports.
+    pub fn render(&self, renderer: &Renderer) -> Result<Image, HeadlessError> {
+        let mut image = Image::new(self.width, self.height);
+        let tile_size = self.tile_size.min(renderer.max_size()).max(1);
+        let row_length = self.width as usize * 4;
+        for tile in tiles::split(self.width, self.height, tile_size) {
+            let rendered =
+                renderer.render_globals(self.tile_globals(tile), tile.width, tile.height)?;
+            let tile_row_length = tile.width as usize * 4;
+            for (y, row) in rendered.pixels.chunks_exact(tile_row_length).enumerate() {
+                let start = (tile.y as usize + y) * row_length + tile.x as usize * 4;
+                image.pixels[start..start + tile_row_length].copy_from_slice(row);
+            }
+        }
+        Ok(image)
+    }
+
+    /// Reproject `view` at `width` by `height` from `map`, the rendered map, filtering bilinearly.
+    ///
+    /// The view should be centered on the map's center and within the radii it covers, parts
+    /// outside of them repeat the outermost or innermost row.
+    pub fn reproject(&self, map: &Image, view: &View, width: u32, height: u32) -> Image {
+        let viewport = Vec2::new(width as f64, height as f64);
+        let pixel_to_complex = view.pixel_to_complex(viewport);
+        let (log_radius, step) = (self.radius.ln(), self.step());
+        let mut image = Image::new(width, height);
+        cpu::for_each_row(&mut image.pixels, width as usize * 4, |y, row| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let point = pixel_to_complex * kurbo::Point::new(x as f64 + 0.5, y as f64 + 0.5);
+                let position = complex_to_exp_map(self.center, log_radius, step, point.to_vec2());
+                pixel.copy_from_slice(&sample_bilinear(map, position - Vec2::new(0.5, 0.5)));
+            }
+        });
+        image
+    }
+}
+
+/// The color of `image` at `position`, in pixel coordinates where pixel centers are whole, with
+/// columns wrapping around and rows clamped.
+fn sample_bilinear(image: &Image, position: Vec2) -> [u8; 4] {
+    let (width, height) = (image.width as i64, image.height as i64);
+    let (x0, y0) = (position.x.floor(), position.y.floor());
+    let (fx, fy) = (position.x - x0, position.y - y0);
+    let texel =
+        |x: i64, y: i64| image.pixel(x.rem_euclid(width) as u32, y.clamp(0, height - 1) as u32);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let [a, b, c, d] = [
+        texel(x0, y0),
+        texel(x0 + 1, y0),
+        texel(x0, y0 + 1),
+        texel(x0 + 1, y0 + 1),
+    ];
+    std::array::from_fn(|i| {
+        let top = a[i] as f64 * (1.0 - fx) + b[i] as f64 * fx;
+        let bottom = c[i] as f64 * (1.0 - fx) + d[i] as f64 * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_covers_zoom() {
+        let center = Vec2::new(-0.7453, 0.1127);
+        let map = ExpMap::for_zoom(center, 1.0, 1000.0, 64, 48);
+        assert_eq!(map.width, (TAU * 40.0).ceil() as u32);
+        // three decades of zoom, and then some to reach the center pixel of the last frame
+        let decades = (map.radius / map.inner_radius()).log10();
+        assert!((3.0..3.0 + 2.0).contains(&decades), "{decades}");
+        let top_left = map.pixel_to_complex(Vec2::ZERO);
+        assert!(((top_left - center).hypot() - map.radius).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let center = Vec2::new(-0.7453, 0.1127);
+        let map = ExpMap::for_zoom(center, 1.0, 1000.0, 64, 48);
+        let metadata = map.metadata(1000.0);
+        assert_eq!(metadata.view, View::new(center, 1000.0));
+        assert_eq!(metadata.palette, map.settings.palette);
+        assert_eq!(metadata.max_iterations, map.max_iterations());
+        let mut png = Vec::new();
+        Image::new(4, 3)
+            .write_png(&mut png, Some(&metadata))
+            .unwrap();
+        assert_eq!(Metadata::read_png(png.as_slice()).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_reprojection_matches_render() {
+        let center = Vec2::new(-0.7453, 0.1127);
+        let (width, height) = (64, 48);
+        let map = ExpMap::for_zoom(center, 20.0, 200.0, width, height);
+        let rendered = map.render(&Renderer::Cpu).unwrap();
+        assert_eq!((rendered.width, rendered.height), (map.width, map.height));
+        let view = View::new(center, 60.0);
+        let reprojected = map.reproject(&rendered, &view, width, height);
+        let settings = Settings {
+            max_iterations: Some(map.max_iterations()),
+            ..Settings::default()
+        };
+        let direct = cpu::render(&view, &settings, width, height);
+        let difference = reprojected
+            .pixels
+            .iter()
+            .zip(&direct.pixels)
+            .map(|(a, b)| a.abs_diff(*b) as f64)
+            .sum::<f64>()
+            / direct.pixels.len() as f64;
+        assert!(difference < 12.0, "{difference}");
+    }
+
+    #[test]
+    fn test_sample_bilinear_wraps() {
+        let mut image = Image::new(2, 1);
+        image
+            .pixels
+            .copy_from_slice(&[0, 0, 0, 255, 200, 100, 50, 255]);
+        assert_eq!(
+            sample_bilinear(&image, Vec2::new(0.5, 0.0)),
+            [100, 50, 25, 255]
+        );
+        // halfway from the last column back around to the first
+        assert_eq!(
+            sample_bilinear(&image, Vec2::new(1.5, 3.0)),
+            [100, 50, 25, 255]
+        );
+        assert_eq!(
+            sample_bilinear(&image, Vec2::new(-1.0, -2.0)),
+            [200, 100, 50, 255]
+        );
+    }
+}
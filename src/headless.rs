@@ -0,0 +1,352 @@
+use std::fmt;
+
+use wgpu::{util::DeviceExt as _, Device, Queue, RenderPipeline, TextureFormat};
+
+use crate::{cpu, image::Image, settings::Settings, tiles, view::View, App, Globals, VERTICES};
+
+/// The format images are rendered in, which is also the byte order of [`Image`].
+const FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+/// Side length of the tiles an image is rendered in. Every tile is submitted on its own so that a
+/// single submission can't take long enough for the driver to reset the GPU.
+const TILE_SIZE: u32 = 512;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// No adapter, not even a software one, is available
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /// The image is larger than a texture can be on this device
+    TooLarge {
+        width: u32,
+        height: u32,
+        max: u32,
+    },
+    ReadBack(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::NoAdapter => write!(f, "no graphics adapter is available"),
+            HeadlessError::RequestDevice(err) => write!(f, "failed to create a device: {err}"),
+            HeadlessError::TooLarge { width, height, max } => write!(
+                f,
+                "{width}x{height} is larger than the maximum texture size of {max}x{max}"
+            ),
+            HeadlessError::ReadBack(err) => write!(f, "failed to read back the image: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+/// Renders images of the set without a window.
+///
+/// This uses the same pipeline as the window, from [`App::pipeline`], but renders into an
+/// offscreen texture and copies the result back into an [`Image`]. No surface is needed so any
+/// adapter will do, including the software fallback adapter on machines without a GPU or display.
+///
+/// ```no_run
+/// use kurbo::Vec2;
+/// use wgpu_mandelbrot::{
+///     headless::HeadlessRenderer, metadata::Metadata, settings::Settings, view::View,
+/// };
+///
+/// let renderer = pollster::block_on(HeadlessRenderer::new()).unwrap();
+/// let view = View::new(Vec2::new(-0.75, 0.1), 20.0);
+/// let settings = Settings::default();
+/// let image = renderer.render(&view, &settings, 800, 600).unwrap();
+/// let metadata = Metadata::new(view, &settings, 800, 600);
+/// image
+///     .save_png("seahorse_valley.png", Some(&metadata))
+///     .unwrap();
+/// ```
+pub struct HeadlessRenderer {
+    adapter_info: wgpu::AdapterInfo,
+    device: Device,
+    queue: Queue,
+    pipeline: RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    globals_buffer: wgpu::Buffer,
+    globals_bind_group: wgpu::BindGroup,
+}
+
+impl HeadlessRenderer {
+    /// Set up rendering on the best adapter available, falling back to a software adapter.
+    pub async fn new() -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let mut adapter = None;
+        for force_fallback_adapter in [false, true] {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    compatible_surface: None,
+                    force_fallback_adapter,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+        let adapter = adapter.ok_or(HeadlessError::NoAdapter)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    // allow images as large as the adapter can handle
+                    required_limits: adapter.limits(),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .map_err(HeadlessError::RequestDevice)?;
+
+        let (globals_buffer, globals_layout, globals_bind_group) =
+            Globals::create_globals_u_buffer(&device);
+        let pipeline = App::pipeline(&device, FORMAT, &globals_layout);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Ok(Self {
+            adapter_info: adapter.get_info(),
+            device,
+            queue,
+            pipeline,
+            vertex_buffer,
+            globals_buffer,
+            globals_bind_group,
+        })
+    }
+
+    /// The adapter images are rendered on.
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// The largest width and height an image can have.
+    pub fn max_size(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
+    }
+
+    /// Render `view` into a `width` by `height` image with `settings`.
+    pub fn render(
+        &self,
+        view: &View,
+        settings: &Settings,
+        width: u32,
+        height: u32,
+    ) -> Result<Image, HeadlessError> {
+        self.render_globals(
+            Globals::for_view(view, settings, width, height),
+            width,
+            height,
+        )
+    }
+
+    pub(crate) fn render_globals(
+        &self,
+        globals: Globals,
+        width: u32,
+        height: u32,
+    ) -> Result<Image, HeadlessError> {
+        let max = self.max_size();
+        if width > max || height > max {
+            return Err(HeadlessError::TooLarge { width, height, max });
+        }
+        let (width, height) = (width.max(1), height.max(1));
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.queue
+            .write_buffer(&self.globals_buffer, 0, bytemuck::cast_slice(&[globals]));
+
+        for (index, tile) in tiles::split(width, height, TILE_SIZE).iter().enumerate() {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Headless Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: if index == 0 {
+                                wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
+                            } else {
+                                wgpu::LoadOp::Load
+                            },
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    ..Default::default()
+                });
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_bind_group(0, &self.globals_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass.set_scissor_rect(tile.x, tile.y, tile.width, tile.height);
+                render_pass.draw(0..VERTICES.len() as u32, 0..1);
+            }
+            self.queue.submit(Some(encoder.finish()));
+            self.device.poll(wgpu::Maintain::Wait);
+        }
+
+        self.read_back(&texture)
+    }
+
+    /// Copy the contents of `texture` back to the CPU.
+    fn read_back(&self, texture: &wgpu::Texture) -> Result<Image, HeadlessError> {
+        read_texture(&self.device, &self.queue, texture)
+    }
+}
+
+/// Copy the contents of `texture`, which has 4 bytes per pixel, back to the CPU and wait for it.
+pub(crate) fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    texture: &wgpu::Texture,
+) -> Result<Image, HeadlessError> {
+    let (width, height) = (texture.width(), texture.height());
+    // rows of a texture copy need to be aligned
+    let bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("the map callback runs once the device was polled")
+        .map_err(HeadlessError::ReadBack)?;
+
+    let mut image = Image::new(width, height);
+    {
+        let data = buffer.slice(..).get_mapped_range();
+        let row_length = width as usize * 4;
+        for (row, padded) in image
+            .pixels
+            .chunks_exact_mut(row_length)
+            .zip(data.chunks_exact(bytes_per_row as usize))
+        {
+            row.copy_from_slice(&padded[..row_length]);
+        }
+    }
+    buffer.unmap();
+    Ok(image)
+}
+
+/// Renders on the GPU when there is an adapter and on the CPU otherwise.
+pub enum Renderer {
+    Gpu(Box<HeadlessRenderer>),
+    Cpu,
+}
+
+impl Renderer {
+    pub async fn new() -> Self {
+        match HeadlessRenderer::new().await {
+            Ok(renderer) => Renderer::Gpu(Box::new(renderer)),
+            Err(err) => {
+                log::warn!("{err}, rendering on the CPU instead");
+                Renderer::Cpu
+            }
+        }
+    }
+
+    /// Render `view` into a `width` by `height` image with `settings`.
+    pub fn render(
+        &self,
+        view: &View,
+        settings: &Settings,
+        width: u32,
+        height: u32,
+    ) -> Result<Image, HeadlessError> {
+        match self {
+            Renderer::Gpu(renderer) => renderer.render(view, settings, width, height),
+            Renderer::Cpu => Ok(cpu::render(view, settings, width, height)),
+        }
+    }
+
+    /// The largest width and height of an image rendered at once.
+    pub fn max_size(&self) -> u32 {
+        match self {
+            Renderer::Gpu(renderer) => renderer.max_size(),
+            Renderer::Cpu => u32::MAX,
+        }
+    }
+
+    pub(crate) fn render_globals(
+        &self,
+        globals: Globals,
+        width: u32,
+        height: u32,
+    ) -> Result<Image, HeadlessError> {
+        match self {
+            Renderer::Gpu(renderer) => renderer.render_globals(globals, width, height),
+            Renderer::Cpu => Ok(cpu::render_globals(&globals, width, height)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_reads_back_image() {
+        let Ok(renderer) = pollster::block_on(HeadlessRenderer::new()) else {
+            eprintln!("skipping, no adapter available");
+            return;
+        };
+        // wider than a row alignment and more than one tile tall to exercise padding and tiling
+        let image = renderer
+            .render(&View::default(), &Settings::default(), 70, 600)
+            .unwrap();
+        assert_eq!(image.pixels.len(), 70 * 600 * 4);
+        assert!(image.pixels.chunks_exact(4).all(|pixel| pixel[3] == 255));
+        // a point outside of the set and one inside of it end up different colors
+        assert_ne!(image.pixel(0, 0), image.pixel(35, 300));
+    }
+}
@@ -0,0 +1,175 @@
+//! Undo and redo of navigation in the window.
+
+use std::collections::VecDeque;
+
+use web_time::{Duration, Instant};
+
+use crate::view::View;
+
+/// Changes of the same kind closer together than this are undone together
+const COALESCE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What kind of change moved the view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Pan,
+    Zoom,
+    Rotate,
+    /// Moving to another location all at once, like resetting the view, which is never
+    /// coalesced with anything
+    Jump,
+}
+
+/// A bounded list of the views that were left, and of the ones that were undone.
+///
+/// Continuous input like dragging or turning the mouse wheel changes the view many times in a
+/// row, those changes are coalesced into a single step as long as they are of the same kind and
+/// follow each other closely, or until [`History::end_gesture`].
+#[derive(Clone, Debug)]
+pub struct History {
+    past: VecDeque<View>,
+    future: Vec<View>,
+    capacity: usize,
+    /// The kind of the last recorded change and when it happened, while later changes of the
+    /// same kind still coalesce with it
+    last_change: Option<(Change, Instant)>,
+}
+
+impl History {
+    /// A history that remembers up to `capacity` views to go back to.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            past: VecDeque::new(),
+            future: Vec::new(),
+            capacity,
+            last_change: None,
+        }
+    }
+
+    /// Record that `change` is about to move the view away from `current` at `now`.
+    pub fn record(&mut self, current: View, change: Change, now: Instant) {
+        let coalesce = self.last_change.is_some_and(|(last, time)| {
+            last == change && change != Change::Jump && now < time + COALESCE_INTERVAL
+        });
+        self.last_change = Some((change, now));
+        if coalesce || self.past.back() == Some(&current) {
+            return;
+        }
+        self.future.clear();
+        self.past.push_back(current);
+        while self.past.len() > self.capacity {
+            self.past.pop_front();
+        }
+    }
+
+    /// Start a new step with the next change, even if it would coalesce with the last one.
+    pub fn end_gesture(&mut self) {
+        self.last_change = None;
+    }
+
+    /// The view to go back to from `current`, if there is one.
+    pub fn undo(&mut self, current: View) -> Option<View> {
+        let view = self.past.pop_back()?;
+        self.future.push(current);
+        self.last_change = None;
+        Some(view)
+    }
+
+    /// The view that was undone last, going back from `current`.
+    pub fn redo(&mut self, current: View) -> Option<View> {
+        let view = self.future.pop()?;
+        self.past.push_back(current);
+        self.last_change = None;
+        Some(view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motion::Motion;
+    use kurbo::Vec2;
+
+    fn view(x: f64) -> View {
+        View::new(Vec2::new(x, 0.0), 1.0)
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let now = Instant::now();
+        let mut history = History::new(10);
+        assert_eq!(history.undo(view(0.0)), None);
+        history.record(view(0.0), Change::Jump, now);
+        history.record(view(1.0), Change::Jump, now);
+        assert_eq!(history.undo(view(2.0)), Some(view(1.0)));
+        assert_eq!(history.undo(view(1.0)), Some(view(0.0)));
+        assert_eq!(history.undo(view(0.0)), None);
+        assert_eq!(history.redo(view(0.0)), Some(view(1.0)));
+        assert_eq!(history.redo(view(1.0)), Some(view(2.0)));
+        assert_eq!(history.redo(view(2.0)), None);
+
+        // a new change drops what was undone
+        history.undo(view(2.0));
+        history.record(view(1.0), Change::Pan, now);
+        assert_eq!(history.redo(view(3.0)), None);
+        assert_eq!(history.undo(view(3.0)), Some(view(1.0)));
+    }
+
+    #[test]
+    fn test_continuous_changes_coalesce() {
+        let start = Instant::now();
+        let mut history = History::new(10);
+        for i in 0..10 {
+            let now = start + Duration::from_millis(100 * i);
+            history.record(view(i as f64), Change::Pan, now);
+        }
+        // a different kind of change, a pause and the end of a gesture each start a new step
+        let later = start + Duration::from_secs(1);
+        history.record(view(10.0), Change::Zoom, later);
+        history.record(view(11.0), Change::Zoom, later + Duration::from_secs(1));
+        history.end_gesture();
+        history.record(view(12.0), Change::Zoom, later + Duration::from_secs(1));
+        history.record(view(13.0), Change::Jump, later + Duration::from_secs(1));
+        history.record(view(14.0), Change::Jump, later + Duration::from_secs(1));
+
+        let mut undone = Vec::new();
+        while let Some(view) = history.undo(view(15.0)) {
+            undone.push(view.center.x);
+        }
+        assert_eq!(undone, [14.0, 13.0, 12.0, 11.0, 10.0, 0.0]);
+    }
+
+    #[test]
+    fn test_undo_during_flight() {
+        let now = Instant::now();
+        let mut history = History::new(10);
+        let mut motion = Motion::new();
+        history.record(view(0.0), Change::Jump, now);
+        history.record(view(1.0), Change::Jump, now);
+        // what the window does, undoing twice before the first flight got anywhere
+        let shown = view(2.0);
+        for expected in [view(1.0), view(0.0)] {
+            let current = motion.target().unwrap_or(shown);
+            let target = history.undo(current).unwrap();
+            assert_eq!(target, expected);
+            motion.transition(shown, target, now);
+        }
+        // redo flies back through the views that were visited, not the one it was at
+        let current = motion.target().unwrap_or(shown);
+        assert_eq!(history.redo(current), Some(view(1.0)));
+        assert_eq!(history.redo(view(1.0)), Some(view(2.0)));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let now = Instant::now();
+        let mut history = History::new(3);
+        for i in 0..5 {
+            history.record(view(i as f64), Change::Jump, now);
+        }
+        assert_eq!(history.undo(view(5.0)), Some(view(4.0)));
+        assert_eq!(history.undo(view(4.0)), Some(view(3.0)));
+        assert_eq!(history.undo(view(3.0)), Some(view(2.0)));
+        assert_eq!(history.undo(view(2.0)), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! The heads-up display of where the window is and how fast it renders.
+
+use std::collections::VecDeque;
+
+use kurbo::Vec2;
+use web_time::{Duration, Instant};
+
+use crate::view::View;
+
+/// How far back frames count towards the frame rate
+const FRAME_WINDOW: Duration = Duration::from_secs(1);
+
+/// Frame times over the last [`FRAME_WINDOW`].
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    frames: VecDeque<Instant>,
+    /// How long the GPU took to render the fractal in the last measured frame
+    gpu_time: Option<Duration>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a frame rendered at `now`.
+    pub fn frame(&mut self, now: Instant) {
+        self.frames.push_back(now);
+        while self
+            .frames
+            .front()
+            .is_some_and(|&time| time + FRAME_WINDOW < now)
+        {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn set_gpu_time(&mut self, gpu_time: Duration) {
+        self.gpu_time = Some(gpu_time);
+    }
+
+    pub fn gpu_time(&self) -> Option<Duration> {
+        self.gpu_time
+    }
+
+    /// The average time between the recent frames, once there are two.
+    pub fn frame_time(&self) -> Option<Duration> {
+        let (first, last) = (self.frames.front()?, self.frames.back()?);
+        let intervals = self.frames.len() as u32 - 1;
+        (intervals > 0).then(|| last.duration_since(*first) / intervals)
+    }
+}
+
+/// What the heads-up display shows.
+#[derive(Clone, Debug)]
+pub struct Readout {
+    pub view: View,
+    /// The complex point under the cursor, if it is over the window
+    pub cursor: Option<Vec2>,
+    /// Width of a pixel in the complex plane, which decides how many digits are worth showing
+    pub pixel_size: f64,
+    pub max_iterations: u32,
+    pub frame_time: Option<Duration>,
+    pub gpu_time: Option<Duration>,
+}
+
+impl Readout {
+    /// The lines of text to display.
+    pub fn lines(&self) -> Vec<String> {
+        // enough digits to tell neighboring pixels apart
+        let digits = (1.0 - self.pixel_size.log10()).ceil().clamp(1.0, 17.0) as usize;
+        let coordinate = |value: Option<f64>| {
+            value.map_or("-".to_string(), |value| format!("{value:+.digits$}"))
+        };
+        let milliseconds = |time: Option<Duration>| {
+            time.map_or("-".to_string(), |time| {
+                format!("{:.1} ms", time.as_secs_f64() * 1000.0)
+            })
+        };
+        let fps = self.frame_time.map_or("-".to_string(), |time| {
+            format!("{:.0}", 1.0 / time.as_secs_f64())
+        });
+        let rotation = self.view.rotation.to_degrees().rem_euclid(360.0);
+        [
+            ("re", coordinate(self.cursor.map(|it| it.x))),
+            ("im", coordinate(self.cursor.map(|it| it.y))),
+            ("zoom", format!("{:.3e}", self.view.zoom)),
+            ("rotation", format!("{rotation:.1}°")),
+            ("iterations", self.max_iterations.to_string()),
+            (
+                "frame",
+                format!("{}  {fps} fps", milliseconds(self.frame_time)),
+            ),
+            ("gpu", milliseconds(self.gpu_time)),
+        ]
+        .into_iter()
+        .map(|(label, value)| format!("{label:<12}{value}"))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_time() {
+        let start = Instant::now();
+        let mut stats = FrameStats::new();
+        stats.frame(start);
+        assert_eq!(stats.frame_time(), None);
+        for i in 1..=100 {
+            stats.frame(start + Duration::from_millis(20 * i));
+        }
+        // only the last second counts
+        assert_eq!(stats.frames.len(), 51);
+        assert_eq!(stats.frame_time(), Some(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_readout_lines() {
+        let readout = Readout {
+            view: View {
+                center: Vec2::new(-0.75, 0.1),
+                zoom: 12340.0,
+                rotation: -std::f64::consts::FRAC_PI_2,
+            },
+            cursor: Some(Vec2::new(-0.7512345678, -0.1)),
+            pixel_size: 2e-6,
+            max_iterations: 1024,
+            frame_time: Some(Duration::from_millis(20)),
+            gpu_time: None,
+        };
+        assert_eq!(
+            readout.lines(),
+            [
+                "re          -0.7512346",
+                "im          -0.1000000",
+                "zoom        1.234e4",
+                "rotation    270.0°",
+                "iterations  1024",
+                "frame       20.0 ms  50 fps",
+                "gpu         -",
+            ]
+        );
+    }
+}
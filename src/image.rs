@@ -0,0 +1,102 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::metadata::Metadata;
+
+/// An 8-bit RGBA image, rows from top to bottom without any padding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// The RGBA value of the pixel at `x`, `y`.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let start = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[start..start + 4].try_into().unwrap()
+    }
+
+    /// Encode the image as a PNG into `writer`, with the view it shows if there is `metadata`.
+    pub fn write_png(
+        &self,
+        writer: impl Write,
+        metadata: Option<&Metadata>,
+    ) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        if let Some(metadata) = metadata {
+            metadata.add_to_png(&mut encoder)?;
+        }
+        encoder.write_header()?.write_image_data(&self.pixels)
+    }
+
+    pub fn save_png(
+        &self,
+        path: impl AsRef<Path>,
+        metadata: Option<&Metadata>,
+    ) -> Result<(), png::EncodingError> {
+        self.write_png(BufWriter::new(File::create(path)?), metadata)
+    }
+
+    /// Decode a PNG from `reader`, converting it to 8-bit RGBA.
+    pub fn read_png(reader: impl Read) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let data = &buffer[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => data.to_vec(),
+            png::ColorType::Rgb => data
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => data
+                .chunks_exact(2)
+                .flat_map(|it| [it[0], it[0], it[0], it[1]])
+                .collect(),
+            // palettes are expanded to RGB(A) when decoding
+            png::ColorType::Grayscale | png::ColorType::Indexed => {
+                data.iter().flat_map(|&it| [it, it, it, 255]).collect()
+            }
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, png::DecodingError> {
+        Self::read_png(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let mut image = Image::new(3, 2);
+        image.pixels[4..8].copy_from_slice(&[10, 20, 30, 40]);
+        let mut png = Vec::new();
+        image.write_png(&mut png, None).unwrap();
+        assert_eq!(Image::read_png(png.as_slice()).unwrap(), image);
+    }
+}
@@ -1,10 +1,64 @@
+pub mod animation;
+pub mod antialiasing;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+pub mod bindings;
+mod blit;
+pub mod bookmarks;
+pub mod budget;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cpu;
+pub mod data;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod exp_map;
+mod font;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+pub mod history;
+pub mod hud;
+pub mod image;
+pub mod metadata;
+pub mod motion;
+pub mod orbit;
+mod overlay;
+pub mod palette;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod poster;
+pub mod progressive;
+pub mod rotation;
+pub mod selection;
+pub mod settings;
+pub mod tiles;
+pub mod touch;
 pub mod transforms;
+pub mod video;
+pub mod view;
 
-use transforms::{aspect_ratio_correction, general_transform};
+use antialiasing::Antialiasing;
+use bindings::{Action, Bindings, Chord};
+use blit::{BlitSource, Blitter};
+use bookmarks::{Bookmark, Bookmarks};
+use budget::{FrameBudget, GpuTimer};
+use history::{Change, History};
+use hud::{FrameStats, Readout};
+use metadata::Metadata;
+use motion::Motion;
+use overlay::{Overlay, RectangleStyle, TextStyle};
+use palette::Palette;
+use progressive::{DynamicResolution, Pass, Refinement};
+use rotation::RotationDrag;
+use selection::Selection;
+use settings::Settings;
+use tiles::Tile;
+use touch::Touches;
+use transforms::{integer_translation, pixel_to_complex};
+use view::View;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use std::{f64::consts::TAU, sync::Arc};
+use std::{path::PathBuf, sync::Arc};
+
+use web_time::{Duration, Instant};
 
 use kurbo::{Affine, Vec2};
 use wgpu::{
@@ -14,17 +68,66 @@ use wgpu::{
 use winit::{
     application::ApplicationHandler,
     event::*,
-    event_loop::{ActiveEventLoop, EventLoop},
-    keyboard::NamedKey,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::ModifiersState,
     window::{Window, WindowId},
 };
+/// GPU time each frame may spend on rendering the fractal
+const FRAME_BUDGET: Duration = Duration::from_millis(12);
+/// How long input needs to stop before the view is rendered at full resolution
+const IDLE_INTERVAL: Duration = Duration::from_millis(250);
+/// The finest downscale that is rendered while dragging or scrolling
+const INTERACTION_DOWNSCALE: u32 = 2;
+/// How many views undo can go back through
+const HISTORY_LENGTH: usize = 100;
+/// How the rectangle dragged out to zoom in to is drawn
+const ZOOM_IN_SELECTION: RectangleStyle = RectangleStyle {
+    fill: [1.0, 1.0, 1.0, 0.15],
+    border: [1.0, 1.0, 1.0, 0.9],
+    border_width: 1.5,
+};
+/// How the heads-up display is drawn
+const HUD_TEXT: TextStyle = TextStyle {
+    color: [1.0, 1.0, 1.0, 0.95],
+    background: [0.0, 0.0, 0.0, 0.55],
+};
+/// Distance of the heads-up display from the top left corner, in logical pixels
+const HUD_MARGIN: f64 = 8.0;
+/// How the rectangle dragged out to zoom out into is drawn
+const ZOOM_OUT_SELECTION: RectangleStyle = RectangleStyle {
+    fill: [0.3, 0.6, 1.0, 0.15],
+    border: [0.3, 0.6, 1.0, 0.9],
+    border_width: 1.5,
+};
+
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Globals {
     transform: [f32; 6],
     _padding: [f32; 2], // Padding to ensure 16-byte alignment
     viewport_size: [f32; 2],
-    _padding2: [f32; 2], // Padding to ensure 16-byte alignment
+    // Fraction of the full iteration budget spent by the current refinement pass
+    iteration_scale: f32,
+    // Number of samples along each axis of a pixel
+    samples: u32,
+    // Offset of the samples within a pixel, for jittered antialiasing
+    jitter: [f32; 2],
+    // Iteration count difference between neighbors that adaptive antialiasing resamples
+    edge_threshold: f32,
+    // Non zero to output the normalized iteration count as alpha instead of 1
+    iteration_alpha: u32,
+    // Iteration limit, 0 to raise it with the zoom level
+    max_iterations: u32,
+    // Parameters of the cosine palette, the vectors only use their first three components
+    palette_frequency: f32,
+    // Center of the log-polar mapping, see `exp_map`
+    exp_map_center: [f32; 2],
+    palette_offset: [f32; 4],
+    palette_amplitude: [f32; 4],
+    palette_phase: [f32; 4],
+    // Log of the radius and the angle at the top left pixel and their change per pixel, which
+    // maps pixels log-polar around `exp_map_center` instead of through `transform` when non zero
+    exp_map: [f32; 4],
 }
 fn transform_from_affine(affine: Affine) -> [f32; 6] {
     let [a, b, c, d, e, f] = affine.as_coeffs();
@@ -34,14 +137,94 @@ fn transform_from_affine(affine: Affine) -> [f32; 6] {
 
 impl Globals {
     fn new() -> Self {
+        let palette = Palette::default();
         Self {
             transform: transform_from_affine(Affine::IDENTITY),
             _padding: [0.0, 0.0],
             viewport_size: [600., 800.],
-            _padding2: [0.0, 0.0],
+            iteration_scale: 1.0,
+            samples: 1,
+            jitter: [0.0, 0.0],
+            edge_threshold: antialiasing::EDGE_THRESHOLD,
+            iteration_alpha: 0,
+            max_iterations: 0,
+            palette_frequency: palette.frequency,
+            exp_map_center: [0.0, 0.0],
+            palette_offset: [palette.offset[0], palette.offset[1], palette.offset[2], 0.0],
+            palette_amplitude: [
+                palette.amplitude[0],
+                palette.amplitude[1],
+                palette.amplitude[2],
+                0.0,
+            ],
+            palette_phase: [palette.phase[0], palette.phase[1], palette.phase[2], 0.0],
+            exp_map: [0.0; 4],
+        }
+    }
+
+    /// The globals for rendering `view` into a still `width` by `height` image with `settings`.
+    pub(crate) fn for_view(view: &View, settings: &Settings, width: u32, height: u32) -> Self {
+        let viewport = Vec2::new(width as f64, height as f64);
+        Self {
+            samples: settings.antialiasing.samples(),
+            ..Self::for_viewport(view.pixel_to_complex(viewport), width, height)
+                .with_settings(settings)
         }
     }
 
+    /// Apply the palette and iteration limit of `settings`. Samples depend on the pass that is
+    /// rendered so they are left alone.
+    pub(crate) fn with_settings(self, settings: &Settings) -> Self {
+        let Palette {
+            offset,
+            amplitude,
+            frequency,
+            phase,
+        } = settings.palette;
+        let extend = |[x, y, z]: [f32; 3]| [x, y, z, 0.0];
+        Self {
+            max_iterations: settings.max_iterations.unwrap_or(0),
+            palette_frequency: frequency,
+            palette_offset: extend(offset),
+            palette_amplitude: extend(amplitude),
+            palette_phase: extend(phase),
+            ..self
+        }
+    }
+
+    /// The palette the shader colors with.
+    pub(crate) fn palette(&self) -> Palette {
+        let truncate = |[x, y, z, _]: [f32; 4]| [x, y, z];
+        Palette {
+            offset: truncate(self.palette_offset),
+            amplitude: truncate(self.palette_amplitude),
+            frequency: self.palette_frequency,
+            phase: truncate(self.palette_phase),
+        }
+    }
+
+    /// The globals for rendering a `width` by `height` image where `pixel_to_complex` maps pixel
+    /// coordinates onto the complex plane.
+    pub(crate) fn for_viewport(pixel_to_complex: Affine, width: u32, height: u32) -> Self {
+        Self {
+            transform: transform_from_affine(pixel_to_complex),
+            viewport_size: [width as f32, height as f32],
+            ..Self::new()
+        }
+    }
+
+    /// The iteration limit that was set, or the one for the zoom level of the transform, which
+    /// the shader computes as `max_i`.
+    pub(crate) fn max_iterations(&self) -> u32 {
+        if self.max_iterations != 0 {
+            return self.max_iterations;
+        }
+        // computed per vertex in the shader, the same for every pixel
+        let [a, b, _, d, ..] = self.transform;
+        let zoom_factor = 1.0 / (a * a + b * b + d * d).sqrt();
+        (100.0 * zoom_factor.log2()) as u32
+    }
+
     fn create_globals_u_buffer(
         device: &Device,
     ) -> (wgpu::Buffer, wgpu::BindGroupLayout, wgpu::BindGroup) {
@@ -108,6 +291,97 @@ const VERTICES: &[Vertex] = &[
     Vertex { position: [-1.0,  1.0] },
 ];
 
+/// A texture that can be rendered into and copied from with a [`Blitter`].
+struct Target {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    source: BlitSource,
+}
+
+impl Target {
+    fn new(device: &Device, config: &SurfaceConfiguration, blitter: &Blitter, label: &str) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let source = blitter.source(device, &view);
+        Self {
+            texture,
+            view,
+            source,
+        }
+    }
+}
+
+/// Offscreen textures the fractal is rendered into before it is copied to the surface.
+struct RenderTargets {
+    /// Holds the latest coarse refinement pass in its top left corner
+    level: Target,
+    /// The best image of the current view rendered so far, at full resolution
+    accumulation: Target,
+    /// The accumulation texture of the view before the last change, reused as a starting point
+    previous: Target,
+    /// Copy of the accumulation texture that adaptive antialiasing looks for edges in
+    snapshot: wgpu::Texture,
+    snapshot_bind_group: BindGroup,
+}
+
+impl RenderTargets {
+    fn new(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        blitter: &Blitter,
+        snapshot_layout: &BindGroupLayout,
+    ) -> Self {
+        let snapshot = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Snapshot Texture"),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let snapshot_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Snapshot Bind Group"),
+            layout: snapshot_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &snapshot.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            }],
+        });
+
+        Self {
+            level: Target::new(device, config, blitter, "Refinement Level Texture"),
+            accumulation: Target::new(device, config, blitter, "Accumulation Texture"),
+            previous: Target::new(device, config, blitter, "Previous Accumulation Texture"),
+            snapshot,
+            snapshot_bind_group,
+        }
+    }
+}
+
 struct WindowState {
     window: Arc<winit::window::Window>,
     device: Arc<Device>,
@@ -116,10 +390,46 @@ struct WindowState {
     surface: Surface<'static>,
     config: SurfaceConfiguration,
     pipeline: RenderPipeline,
+    /// Blends jittered samples into the accumulated image
+    jitter_pipeline: RenderPipeline,
+    /// Resamples the pixels of the accumulated image that are on edges
+    adaptive_pipeline: RenderPipeline,
     vertex_buffer: wgpu::Buffer,
+    globals: Globals,
     globals_buffer: wgpu::Buffer,
     globals_bind_group: BindGroup,
     num_vertices: u32,
+    blitter: Blitter,
+    overlay: Overlay,
+    snapshot_layout: BindGroupLayout,
+    targets: RenderTargets,
+    settings: Settings,
+    refinement: Refinement,
+    budget: FrameBudget,
+    timer: GpuTimer,
+    /// The transform the accumulation texture was rendered with, if it holds anything usable
+    accumulated_transform: Option<Affine>,
+    dynamic_resolution: DynamicResolution,
+    /// Panning, zooming and transitions still playing out after the input that started them
+    motion: Motion,
+    history: History,
+    /// What keys and mouse buttons do
+    bindings: Bindings,
+    bookmarks: Bookmarks,
+    /// Where bookmarks are saved, `None` if they can't be
+    bookmarks_path: Option<PathBuf>,
+    /// The bookmark that was jumped to last, which cycling through them continues from
+    bookmark_index: Option<usize>,
+    /// The rectangle being dragged out to zoom to, if any
+    selection: Option<Selection>,
+    /// The rotation being dragged with ctrl held, if any
+    rotation: Option<RotationDrag>,
+    /// Whether the heads-up display is shown
+    show_hud: bool,
+    frame_stats: FrameStats,
+    modifiers: ModifiersState,
+    /// The fingers on a touchscreen
+    touches: Touches,
     mouse_down: bool,
     transform: Affine,
     prior_mouse_pos: Option<Vec2>,
@@ -159,6 +469,41 @@ impl WindowState {
         let (globals_u_buffer, globals_u_group_layout, globals_group) =
             Globals::create_globals_u_buffer(&device);
         let pipeline = App::pipeline(&device, texture_format, &globals_u_group_layout);
+        let snapshot_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Snapshot Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let blend_constant = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let jitter_pipeline = App::fractal_pipeline(
+            &device,
+            texture_format,
+            &[&globals_u_group_layout],
+            "fs_main",
+            wgpu::BlendState {
+                color: blend_constant,
+                alpha: blend_constant,
+            },
+        );
+        let adaptive_pipeline = App::fractal_pipeline(
+            &device,
+            texture_format,
+            &[&globals_u_group_layout, &snapshot_layout],
+            "fs_adaptive",
+            wgpu::BlendState::REPLACE,
+        );
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -168,6 +513,31 @@ impl WindowState {
 
         let num_vertices = VERTICES.len() as u32;
 
+        let blitter = Blitter::new(&device, texture_format);
+        let overlay = Overlay::new(&device, texture_format);
+        let (bookmarks, bookmarks_path) = match Bookmarks::default_path() {
+            Some(path) => match Bookmarks::load(&path) {
+                Ok(bookmarks) => (bookmarks, Some(path)),
+                Err(err) => {
+                    // rather than overwriting a file that can't be read
+                    log::warn!("not saving bookmarks, {}: {err}", path.display());
+                    (Bookmarks::famous(), None)
+                }
+            },
+            None => (Bookmarks::famous(), None),
+        };
+        let bindings = match Bindings::default_path() {
+            Some(path) => Bindings::load(&path).unwrap_or_else(|err| {
+                log::warn!("using the default bindings, {}: {err}", path.display());
+                Bindings::default()
+            }),
+            None => Bindings::default(),
+        };
+        let targets = RenderTargets::new(&device, &config, &blitter, &snapshot_layout);
+        let timer = GpuTimer::new(&device, &queue);
+        let settings = Settings::default();
+        let refinement = Refinement::new(config.width, config.height, settings.antialiasing);
+
         Self {
             window,
             device: Arc::new(device),
@@ -175,47 +545,51 @@ impl WindowState {
             surface,
             config,
             pipeline,
+            jitter_pipeline,
+            adaptive_pipeline,
             vertex_buffer,
             num_vertices,
+            globals: Globals::new(),
             globals_buffer: globals_u_buffer,
             globals_bind_group: globals_group,
+            blitter,
+            overlay,
+            snapshot_layout,
+            targets,
+            settings,
+            refinement,
+            budget: FrameBudget::new(FRAME_BUDGET),
+            timer,
+            accumulated_transform: None,
+            dynamic_resolution: DynamicResolution::new(IDLE_INTERVAL, INTERACTION_DOWNSCALE),
+            motion: Motion::new(),
+            history: History::new(HISTORY_LENGTH),
+            bindings,
+            bookmarks,
+            bookmarks_path,
+            bookmark_index: None,
+            selection: None,
+            rotation: None,
+            show_hud: false,
+            frame_stats: FrameStats::new(),
+            modifiers: ModifiersState::empty(),
+            touches: Touches::new(),
             mouse_down: false,
             prior_mouse_pos: None,
             transform: Affine::IDENTITY,
         }
     }
 
-    fn update_globals(&self) {
+    fn update_globals(&mut self) {
         // define the viewport
-        let viewport = Vec2::new(self.config.width as f64, self.config.height as f64);
-
-        let mandelbrot_min = Vec2::new(-2.0, -1.0);
-        let mandelbrot_max = Vec2::new(1.0, 1.0);
-        let viewport_to_mandelbrot =
-            general_transform(Vec2::new(0., 0.), viewport, mandelbrot_min, mandelbrot_max);
-
-        // Calculate the aspect ratio of the Mandelbrot space
-        let mandelbrot_aspect_ratio =
-            (mandelbrot_max.x - mandelbrot_min.x) / (mandelbrot_max.y - mandelbrot_min.y);
-
-        // Compute the aspect ratio correction
-        let aspect_ratio_correction = aspect_ratio_correction(
-            viewport.x / viewport.y, // Aspect ratio of the viewport
-            mandelbrot_aspect_ratio, // Aspect ratio of the Mandelbrot space
-        )
-        .inverse();
-
-        let final_transform =
-            aspect_ratio_correction * viewport_to_mandelbrot * self.transform.inverse();
+        let final_transform = pixel_to_complex(self.viewport(), self.transform);
 
-        let uniforms = Globals {
-            transform: transform_from_affine(final_transform),
-            _padding: [0.0, 0.0],
-            viewport_size: [viewport.x as f32, viewport.y as f32],
-            _padding2: [0.0, 0.0],
+        self.globals = Globals {
+            // adaptive antialiasing finds edges in the iteration counts of the accumulated image
+            iteration_alpha: 1,
+            ..Globals::for_viewport(final_transform, self.config.width, self.config.height)
+                .with_settings(&self.settings)
         };
-        self.queue
-            .write_buffer(&self.globals_buffer, 0, bytemuck::cast_slice(&[uniforms]));
         self.window.request_redraw();
     }
 
@@ -223,10 +597,31 @@ impl WindowState {
         self.config.width = size.width;
         self.config.height = size.height;
         self.surface.configure(&self.device, &self.config);
+        self.targets = RenderTargets::new(
+            &self.device,
+            &self.config,
+            &self.blitter,
+            &self.snapshot_layout,
+        );
+        self.accumulated_transform = None;
         self.update_globals();
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // collect the timing of an earlier frame, if it has arrived
+        self.device.poll(wgpu::Maintain::Poll);
+        if let Some((elapsed, work)) = self.timer.finished() {
+            self.budget.record(elapsed, work);
+            self.frame_stats.set_gpu_time(elapsed);
+        }
+        let now = Instant::now();
+        self.frame_stats.frame(now);
+        if let Some(transform) = self.motion.step(self.transform, self.viewport(), now) {
+            self.transform = transform;
+            self.dynamic_resolution.interacted(now);
+            self.update_globals();
+        }
+
         let frame = self.surface.get_current_texture()?;
         let view = frame
             .texture
@@ -235,54 +630,688 @@ impl WindowState {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        {
-            let clear_color = wgpu::Color {
-                r: 0.1,
-                g: 0.2,
-                b: 0.3,
-                a: 0.0,
+        if self.accumulated_transform != Some(self.transform) {
+            self.view_changed(&mut encoder);
+        }
+        self.refinement
+            .set_finest_downscale(self.dynamic_resolution.finest_downscale(Instant::now()));
+
+        // Only as many tiles of the current refinement level as fit in the frame budget are
+        // rendered each frame so that input keeps being handled while the image converges. Once
+        // converged the accumulated image is just presented again.
+        let mut work = 0.0;
+        if let Some(pass) = self.refinement.current() {
+            if pass == Pass::Adaptive && self.refinement.is_pass_start() {
+                self.snapshot_accumulation(&mut encoder);
+            }
+            let count = self.budget.tiles_per_frame(
+                self.refinement.pending_tiles(),
+                pass.iteration_scale(),
+                pass.samples(self.settings.antialiasing),
+            );
+            let tiles = self.refinement.take_tiles(count);
+            work = self.render_tiles(&mut encoder, pass, &tiles);
+            if self.refinement.level_finished() {
+                if let Pass::Level(level) = pass {
+                    self.finish_level(&mut encoder, level);
+                }
+                self.refinement.advance();
+            }
+        }
+        self.blitter
+            .present(&mut encoder, &self.targets.accumulation.source, &view);
+        if let Some(selection) = self.selection {
+            let style = if selection.zoom_out {
+                ZOOM_OUT_SELECTION
+            } else {
+                ZOOM_IN_SELECTION
             };
+            self.overlay
+                .rectangle(&self.queue, &mut encoder, &view, selection.bounds(), style);
+        }
+        if self.show_hud {
+            let scale = self.window.scale_factor();
+            self.overlay.text(
+                &self.queue,
+                &mut encoder,
+                &view,
+                self.viewport(),
+                Vec2::new(HUD_MARGIN, HUD_MARGIN) * scale,
+                (2.0 * scale).round().max(1.0) as f32,
+                &self.readout().lines(),
+                HUD_TEXT,
+            );
+        }
+        self.queue.submit(Some(encoder.finish()));
+        if work > 0.0 && self.timer.is_idle() {
+            self.timer.submitted(&self.queue, work);
+        }
+
+        frame.present();
+        if !self.refinement.is_idle() || self.motion.is_moving() {
+            self.window.request_redraw();
+        }
+        Ok(())
+    }
+
+    /// Record rendering `tiles` of a refinement pass and return the amount of work submitted.
+    ///
+    /// Coarse levels are rendered into the top left corner of the level texture. The full
+    /// resolution level and the antialiasing passes are rendered straight into the accumulation
+    /// texture.
+    fn render_tiles(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        pass: Pass,
+        tiles: &[Tile],
+    ) -> f64 {
+        if tiles.is_empty() {
+            return 0.0;
+        }
+        let samples = pass.samples(self.settings.antialiasing);
+        let (pipeline, jitter) = match pass {
+            Pass::Level(_) => (&self.pipeline, [0.0, 0.0]),
+            Pass::Adaptive => (&self.adaptive_pipeline, [0.0, 0.0]),
+            Pass::Jitter { sample } => (&self.jitter_pipeline, antialiasing::jitter(sample)),
+        };
+        self.globals.iteration_scale = pass.iteration_scale();
+        self.globals.samples = samples;
+        self.globals.jitter = jitter;
+        self.queue.write_buffer(
+            &self.globals_buffer,
+            0,
+            bytemuck::cast_slice(&[self.globals]),
+        );
+
+        let (width, height) = pass.size(self.config.width, self.config.height);
+        let target = match pass {
+            Pass::Level(level) if level.downscale != 1 => &self.targets.level.view,
+            _ => &self.targets.accumulation.view,
+        };
+
+        let measure = self.timer.is_idle();
+        {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: target,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(clear_color),
+                        // tiles rendered in earlier frames need to be kept
+                        load: wgpu::LoadOp::Load,
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: None,
+                timestamp_writes: if measure {
+                    self.timer.timestamp_writes()
+                } else {
+                    None
+                },
                 ..Default::default()
             });
-            render_pass.set_pipeline(&self.pipeline); // 2.
+            render_pass.set_viewport(0.0, 0.0, width as f32, height as f32, 0.0, 1.0);
+            render_pass.set_pipeline(pipeline); // 2.
             render_pass.set_bind_group(0, &self.globals_bind_group, &[]);
+            match pass {
+                Pass::Adaptive => {
+                    render_pass.set_bind_group(1, &self.targets.snapshot_bind_group, &[]);
+                }
+                Pass::Jitter { sample } => {
+                    // every sample gets the same weight in the average
+                    let weight = 1.0 / (sample as f64 + 1.0);
+                    render_pass.set_blend_constant(wgpu::Color {
+                        r: weight,
+                        g: weight,
+                        b: weight,
+                        a: weight,
+                    });
+                }
+                Pass::Level(_) => {}
+            }
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..self.num_vertices, 0..1); // 3.
+            for tile in tiles {
+                render_pass.set_scissor_rect(tile.x, tile.y, tile.width, tile.height);
+                render_pass.draw(0..self.num_vertices, 0..1); // 3.
+            }
+        }
+        if measure {
+            self.timer.resolve(encoder);
         }
-        self.queue.submit(Some(encoder.finish()));
 
-        frame.present();
-        Ok(())
+        tiles
+            .iter()
+            .map(|tile| budget::work(tile, pass.iteration_scale(), samples))
+            .sum()
+    }
+
+    /// Record copying the accumulated image to where adaptive antialiasing reads it from.
+    fn snapshot_accumulation(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_texture(
+            self.targets.accumulation.texture.as_image_copy(),
+            self.targets.snapshot.as_image_copy(),
+            self.targets.snapshot.size(),
+        );
+    }
+
+    fn viewport(&self) -> Vec2 {
+        Vec2::new(self.config.width as f64, self.config.height as f64)
+    }
+
+    fn set_view(&mut self, view: View) {
+        self.transform = view.to_transform(self.viewport());
+        self.update_globals();
+    }
+
+    /// The view the window shows right now.
+    fn view(&self) -> View {
+        View::from_transform(self.transform, self.viewport())
+    }
+
+    /// Move to `view` in an animated transition from the current one.
+    fn fly_to(&mut self, view: View) {
+        self.motion.transition(self.view(), view, Instant::now());
+        self.window.request_redraw();
+    }
+
+    /// Like [`WindowState::fly_to`] but as a step that can be undone.
+    fn jump_to(&mut self, view: View) {
+        self.record(Change::Jump);
+        self.fly_to(view);
+    }
+
+    /// Remember the current view before `change` moves away from it.
+    fn record(&mut self, change: Change) {
+        self.history
+            .record(self.settled_view(), change, Instant::now());
+    }
+
+    fn undo(&mut self) {
+        if let Some(view) = self.history.undo(self.settled_view()) {
+            self.fly_to(view);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(view) = self.history.redo(self.settled_view()) {
+            self.fly_to(view);
+        }
+    }
+
+    /// The view the window is at, or the one it is flying to, which is the view that counts for
+    /// the history since the user never stopped anywhere in between.
+    fn settled_view(&self) -> View {
+        self.motion.target().unwrap_or_else(|| self.view())
+    }
+
+    /// The point keyboard zooming and rotating happen about, the cursor if it is over the window
+    /// and else the center.
+    fn anchor(&self) -> Vec2 {
+        self.cursor().unwrap_or(self.viewport() / 2.0)
+    }
+
+    /// Where the cursor is, if it is over the window.
+    fn cursor(&self) -> Option<Vec2> {
+        let viewport = self.viewport();
+        self.prior_mouse_pos
+            .filter(|it| (0.0..viewport.x).contains(&it.x) && (0.0..viewport.y).contains(&it.y))
+    }
+
+    /// What the heads-up display shows right now.
+    fn readout(&self) -> Readout {
+        // the same transform the shader maps pixels to the complex plane with
+        let to_complex = pixel_to_complex(self.viewport(), self.transform);
+        Readout {
+            view: self.view(),
+            cursor: self
+                .cursor()
+                .map(|cursor| (to_complex * cursor.to_point()).to_vec2()),
+            pixel_size: to_complex.determinant().abs().sqrt(),
+            max_iterations: self.globals.max_iterations(),
+            frame_time: self.frame_stats.frame_time(),
+            gpu_time: self.frame_stats.gpu_time(),
+        }
+    }
+
+    /// Move the image by `delta` pixels in smooth steps.
+    fn pan_by(&mut self, delta: Vec2) {
+        let now = Instant::now();
+        self.record(Change::Pan);
+        self.motion.pan_by(delta, now);
+        self.dynamic_resolution.interacted(now);
+        self.window.request_redraw();
+    }
+
+    /// Zoom by a factor of `e^log_factor` about `anchor` in smooth steps.
+    fn zoom_by(&mut self, anchor: Vec2, log_factor: f64) {
+        let now = Instant::now();
+        self.record(Change::Zoom);
+        self.motion.zoom_by(anchor, log_factor, now);
+        self.dynamic_resolution.interacted(now);
+        self.window.request_redraw();
+    }
+
+    /// Apply `delta` to the window transform right away, as a `change` that can be undone.
+    fn move_view(&mut self, change: Change, delta: Affine) {
+        self.record(change);
+        self.transform = delta * self.transform;
+        self.dynamic_resolution.interacted(Instant::now());
+        self.update_globals();
+    }
+
+    /// Turn the image clockwise by `angle` radians about [`WindowState::anchor`].
+    fn rotate_by(&mut self, angle: f64) {
+        let anchor = self.anchor();
+        let rotation =
+            Affine::translate(anchor) * Affine::rotate(angle) * Affine::translate(-anchor);
+        self.move_view(Change::Rotate, rotation);
+    }
+
+    /// Straighten the view to the nearest multiple of the snap angle, or all the way if snapping
+    /// is off.
+    fn snap_rotation(&mut self) {
+        let mut view = self.view();
+        view.rotation = match self.bindings.snap_angle {
+            snap if snap > 0.0 => rotation::snap_angle(view.rotation, snap),
+            _ => 0.0,
+        };
+        self.jump_to(view);
+    }
+
+    /// Start turning the view by dragging from the cursor.
+    fn start_rotation(&mut self) {
+        if let Some(position) = self.prior_mouse_pos {
+            self.rotation = Some(RotationDrag::new(
+                self.bindings.rotation_pivot,
+                position,
+                self.viewport(),
+                self.transform,
+                self.view().rotation,
+            ));
+        }
+    }
+
+    /// Turn the view as far as the cursor at `position` was dragged, snapping while shift is held.
+    fn drag_rotation(&mut self, drag: RotationDrag, position: Vec2) {
+        let snap = self
+            .modifiers
+            .shift_key()
+            .then_some(self.bindings.snap_angle);
+        let delta = drag.update(position, snap) * self.transform.inverse();
+        self.move_view(Change::Rotate, delta);
+    }
+
+    /// Pan with one finger, and zoom and rotate with more.
+    fn touch(&mut self, touch: Touch) {
+        let now = Instant::now();
+        let position = Vec2::new(touch.location.x, touch.location.y);
+        match touch.phase {
+            TouchPhase::Started => {
+                if self.touches.is_empty() {
+                    self.dynamic_resolution.set_held(true, now);
+                }
+                // how fast one finger moved says nothing about where a pinch is going
+                self.motion.grab();
+                self.history.end_gesture();
+                self.touches.start(touch.id, position);
+            }
+            TouchPhase::Moved => {
+                let Some(delta) = self.touches.moved(touch.id, position) else {
+                    return;
+                };
+                if self.touches.len() == 1 {
+                    self.move_view(Change::Pan, delta);
+                    self.motion.drag(delta.translation(), now);
+                } else {
+                    self.move_view(Change::Zoom, delta);
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let panning = self.touches.len() == 1;
+                self.touches.end(touch.id);
+                if !self.touches.is_empty() {
+                    self.motion.grab();
+                    return;
+                }
+                self.dynamic_resolution.set_held(false, now);
+                if panning && touch.phase == TouchPhase::Ended {
+                    // flicking keeps the view moving like letting go of a mouse drag
+                    self.motion.release(now);
+                }
+                self.window.request_redraw();
+            }
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        let viewport = self.viewport();
+        let pan = self.bindings.pan_step * viewport.x.min(viewport.y);
+        let zoom = self.bindings.zoom_step.ln();
+        let fine_zoom = self.bindings.fine_zoom_step.ln();
+        let rotation = self.bindings.rotation_step;
+        match action {
+            // the image moves the other way than the view
+            Action::PanLeft => self.pan_by(Vec2::new(pan, 0.0)),
+            Action::PanRight => self.pan_by(Vec2::new(-pan, 0.0)),
+            Action::PanUp => self.pan_by(Vec2::new(0.0, pan)),
+            Action::PanDown => self.pan_by(Vec2::new(0.0, -pan)),
+            Action::ZoomIn => self.zoom_by(self.anchor(), zoom),
+            Action::ZoomOut => self.zoom_by(self.anchor(), -zoom),
+            Action::ZoomInFine => self.zoom_by(self.anchor(), fine_zoom),
+            Action::ZoomOutFine => self.zoom_by(self.anchor(), -fine_zoom),
+            Action::RotateCw => self.rotate_by(rotation),
+            Action::RotateCcw => self.rotate_by(-rotation),
+            Action::SnapRotation => self.snap_rotation(),
+            Action::Reset => self.jump_to(View::default()),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Screenshot => self.screenshot(),
+            Action::CycleAntialiasing => self.cycle_antialiasing(),
+            Action::Bookmark => self.add_bookmark(),
+            Action::NextBookmark => self.cycle_bookmarks(1),
+            Action::PreviousBookmark => self.cycle_bookmarks(-1),
+            Action::JumpToBookmark(index) => self.jump_to_bookmark(index),
+            Action::ToggleHud => {
+                self.show_hud = !self.show_hud;
+                self.window.request_redraw();
+            }
+            Action::Cancel => {
+                self.selection = None;
+                self.window.request_redraw();
+            }
+        }
+    }
+
+    /// Save the image in the window as a PNG in the pictures directory, or else the current one.
+    fn screenshot(&self) {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                log::warn!("screenshots can't be saved in the browser");
+            } else {
+                let texture = &self.targets.accumulation.texture;
+                let mut image = match headless::read_texture(&self.device, &self.queue, texture) {
+                    Ok(image) => image,
+                    Err(err) => {
+                        log::warn!("{err}");
+                        return;
+                    }
+                };
+                for pixel in image.pixels.chunks_exact_mut(4) {
+                    if self.config.format == TextureFormat::Bgra8Unorm {
+                        pixel.swap(0, 2);
+                    }
+                    // alpha holds iteration counts for adaptive antialiasing
+                    pixel[3] = 255;
+                }
+                let seconds = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs());
+                let path = dirs::picture_dir()
+                    .unwrap_or_default()
+                    .join(format!("mandelbrot-{seconds}.png"));
+                let (width, height) = (image.width, image.height);
+                let metadata = Metadata::new(self.view(), &self.settings, width, height);
+                match image.save_png(&path, Some(&metadata)) {
+                    Ok(()) => log::info!("saved a screenshot to {}", path.display()),
+                    Err(err) => log::warn!("can't save {}: {err}", path.display()),
+                }
+            }
+        }
+    }
+
+    /// Start dragging out a rectangle to zoom to from the cursor.
+    fn start_selection(&mut self) {
+        if let Some(position) = self.prior_mouse_pos {
+            self.motion.grab();
+            self.selection = Some(Selection::new(position, self.modifiers.alt_key()));
+        }
+    }
+
+    /// Zoom to the rectangle that was dragged out, unless it was too small.
+    fn finish_selection(&mut self) {
+        let Some(selection) = self.selection.take() else {
+            return;
+        };
+        if let Some(zoom) = selection.zoom(self.viewport()) {
+            self.jump_to(View::from_transform(zoom * self.transform, self.viewport()));
+        }
+        // the rectangle needs to be cleared either way
+        self.window.request_redraw();
+    }
+
+    /// Save the current view as a new bookmark.
+    fn add_bookmark(&mut self) {
+        let mut bookmark = Bookmark::new(self.bookmarks.unused_name(), self.view());
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.bookmarks_path {
+            if let Err(err) = Bookmarks::save_thumbnail(&mut bookmark, path, &self.settings) {
+                log::warn!("{err}");
+            }
+        }
+        log::info!("bookmarked the view as {}", bookmark.name);
+        self.bookmarks.add(bookmark);
+        self.bookmark_index = Some(self.bookmarks.len() - 1);
+        if let Some(path) = &self.bookmarks_path {
+            if let Err(err) = self.bookmarks.save(path) {
+                log::warn!("{err}");
+            }
+        }
+    }
+
+    /// Jump to the bookmark at `index`, if there is one.
+    fn jump_to_bookmark(&mut self, index: usize) {
+        let Some(bookmark) = self.bookmarks.iter().nth(index) else {
+            return;
+        };
+        log::info!("{}: {}", bookmark.name, bookmark.notes);
+        self.bookmark_index = Some(index);
+        self.jump_to(bookmark.view);
+    }
+
+    /// Jump to the bookmark `step` places after the last one that was jumped to.
+    fn cycle_bookmarks(&mut self, step: isize) {
+        let len = self.bookmarks.len() as isize;
+        if len == 0 {
+            return;
+        }
+        let index = match self.bookmark_index {
+            Some(index) => (index as isize + step).rem_euclid(len),
+            None if step < 0 => len - 1,
+            None => 0,
+        };
+        self.jump_to_bookmark(index as usize);
+    }
+
+    /// Show the view stored in the metadata of an exported image.
+    fn open_image(&mut self, path: &std::path::Path) {
+        match Metadata::load_png(path) {
+            Ok(metadata) => {
+                if metadata.formula != metadata::FORMULA {
+                    log::warn!(
+                        "{} was rendered with {}, showing its view with {}",
+                        path.display(),
+                        metadata.formula,
+                        metadata::FORMULA
+                    );
+                }
+                self.set_settings(metadata.apply(self.settings));
+                self.jump_to(metadata.view);
+            }
+            Err(err) => log::warn!("can't open {}: {err}", path.display()),
+        }
+    }
+
+    fn cycle_antialiasing(&mut self) {
+        self.set_antialiasing(self.settings.antialiasing.next());
+    }
+
+    fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.settings.antialiasing = antialiasing;
+        log::info!("antialiasing: {:?}", antialiasing);
+        self.refinement.set_antialiasing(antialiasing);
+        self.window.request_redraw();
+    }
+
+    /// Start out showing `view` with `settings`.
+    fn open(&mut self, view: Option<View>, settings: Settings) {
+        self.set_settings(settings);
+        if let Some(view) = view {
+            self.set_view(view);
+        }
+    }
+
+    fn set_settings(&mut self, settings: Settings) {
+        self.set_antialiasing(settings.antialiasing);
+        self.settings = settings;
+        // the accumulated image was colored with the old settings
+        self.accumulated_transform = None;
+        self.update_globals();
+    }
+
+    /// Record stretching a completed coarse level over the accumulation texture.
+    fn finish_level(&mut self, encoder: &mut wgpu::CommandEncoder, level: progressive::Level) {
+        if level.downscale == 1 {
+            return;
+        }
+        let (width, height) = level.size(self.config.width, self.config.height);
+        self.targets.level.source.set_uv_scale(
+            &self.queue,
+            [
+                width as f32 / self.config.width as f32,
+                height as f32 / self.config.height as f32,
+            ],
+        );
+        self.blitter.blit(
+            encoder,
+            &self.targets.level.source,
+            &self.targets.accumulation.view,
+        );
+    }
+
+    /// Start refining a new view, reusing whatever of the previous image still applies.
+    ///
+    /// The previous image is warped into place as a placeholder until the first refinement level
+    /// is done. If the view only moved by whole pixels while already refining at full resolution,
+    /// the pixels are instead copied over exactly and only the strips that scrolled into view are
+    /// rendered.
+    fn view_changed(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let (width, height) = (self.config.width, self.config.height);
+        let Some(previous_transform) = self.accumulated_transform.replace(self.transform) else {
+            self.refinement.restart(width, height);
+            return;
+        };
+        // maps pixels of the new view to the pixels that showed the same point before
+        let to_previous = previous_transform * self.transform.inverse();
+
+        std::mem::swap(&mut self.targets.accumulation, &mut self.targets.previous);
+        let texture_size = Affine::scale_non_uniform(width as f64, height as f64);
+        self.targets.previous.source.set_uv_transform(
+            &self.queue,
+            texture_size.inverse() * to_previous * texture_size,
+        );
+        self.targets
+            .accumulation
+            .source
+            .set_uv_transform(&self.queue, Affine::IDENTITY);
+        self.blitter.blit(
+            encoder,
+            &self.targets.previous.source,
+            &self.targets.accumulation.view,
+        );
+
+        match integer_translation(to_previous.inverse()) {
+            Some((dx, dy)) if self.refinement.translate(dx, dy) => {
+                // the source and destination of the part that stayed in view, along one axis
+                let overlap = |offset: i64, size: u32| {
+                    let length = (size as i64 - offset.abs()).max(0) as u32;
+                    let (from, to) = if offset >= 0 {
+                        (0, offset)
+                    } else {
+                        (-offset, 0)
+                    };
+                    (from as u32, to as u32, length)
+                };
+                let (from_x, to_x, copy_width) = overlap(dx, width);
+                let (from_y, to_y, copy_height) = overlap(dy, height);
+                if copy_width > 0 && copy_height > 0 {
+                    encoder.copy_texture_to_texture(
+                        wgpu::ImageCopyTexture {
+                            texture: &self.targets.previous.texture,
+                            mip_level: 0,
+                            origin: wgpu::Origin3d {
+                                x: from_x,
+                                y: from_y,
+                                z: 0,
+                            },
+                            aspect: wgpu::TextureAspect::All,
+                        },
+                        wgpu::ImageCopyTexture {
+                            texture: &self.targets.accumulation.texture,
+                            mip_level: 0,
+                            origin: wgpu::Origin3d {
+                                x: to_x,
+                                y: to_y,
+                                z: 0,
+                            },
+                            aspect: wgpu::TextureAspect::All,
+                        },
+                        wgpu::Extent3d {
+                            width: copy_width,
+                            height: copy_height,
+                            depth_or_array_layers: 1,
+                        },
+                    );
+                }
+            }
+            _ => self.refinement.restart(width, height),
+        }
     }
 }
 
 #[derive(Default)]
 pub struct App {
     window_state: Option<WindowState>,
+    /// What the window shows once it opens, the whole set if `None`
+    initial_view: Option<View>,
+    settings: Settings,
 }
 
 impl App {
+    pub fn new(initial_view: Option<View>, settings: Settings) -> Self {
+        Self {
+            window_state: None,
+            initial_view,
+            settings,
+        }
+    }
+
     fn pipeline(
         device: &Device,
         format: TextureFormat,
         uniform_group_layout: &BindGroupLayout,
+    ) -> RenderPipeline {
+        Self::fractal_pipeline(
+            device,
+            format,
+            &[uniform_group_layout],
+            "fs_main",
+            wgpu::BlendState::REPLACE,
+        )
+    }
+
+    /// A pipeline that draws the fractal with the fragment shader `entry_point`.
+    fn fractal_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        bind_group_layouts: &[&BindGroupLayout],
+        entry_point: &str,
+        blend: wgpu::BlendState,
     ) -> RenderPipeline {
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[uniform_group_layout],
+            bind_group_layouts,
             push_constant_ranges: &[],
         });
 
@@ -298,11 +1327,11 @@ impl App {
             fragment: Some(wgpu::FragmentState {
                 // 3.
                 module: &shader,
-                entry_point: "fs_main",
+                entry_point,
                 targets: &[Some(wgpu::ColorTargetState {
                     // 4.
                     format,
-                    blend: Some(wgpu::BlendState::REPLACE),
+                    blend: Some(blend),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -379,6 +1408,8 @@ impl ApplicationHandler for App {
                 let (device, queue) = adapter
                     .request_device(
                         &wgpu::DeviceDescriptor {
+                            // used to measure how long frames take on the GPU when available
+                            required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
                             required_limits: if cfg!(target_arch = "wasm32") {
                                 wgpu::Limits::downlevel_webgl2_defaults()
                             } else {
@@ -398,12 +1429,14 @@ impl ApplicationHandler for App {
             {
                 let window_clone = window.clone();
                 let app_ptr = self as *mut App;
+                let (initial_view, settings) = (self.initial_view, self.settings);
 
                 wasm_bindgen_futures::spawn_local(async move {
                     let (adapter, device, queue, surface) = setup_future.await;
 
-                    let window_state =
+                    let mut window_state =
                         WindowState::new(window_clone.clone(), adapter, device, queue, surface);
+                    window_state.open(initial_view, settings);
 
                     unsafe {
                         (*app_ptr).window_state = Some(window_state);
@@ -417,8 +1450,9 @@ impl ApplicationHandler for App {
             {
                 let (adapter, device, queue, surface) = pollster::block_on(setup_future);
 
-                let window_state =
+                let mut window_state =
                     WindowState::new(window.clone(), adapter, device, queue, surface);
+                window_state.open(self.initial_view, self.settings);
 
                 self.window_state = Some(window_state);
 
@@ -453,60 +1487,136 @@ impl ApplicationHandler for App {
                 }
             }
 
+            WindowEvent::DroppedFile(path) => {
+                if let Some(window_state) = &mut self.window_state {
+                    window_state.open_image(&path);
+                }
+            }
             WindowEvent::CursorLeft { .. } => {
-                if let Some(_window_state) = &mut self.window_state {
-                    // window_state.mouse_down = false;
-                    // window_state.prior_mouse_pos = None;
+                if let Some(window_state) = &mut self.window_state {
+                    // keyboard zoom goes back to the center, unless a drag carries on outside
+                    if !window_state.mouse_down && window_state.selection.is_none() {
+                        window_state.prior_mouse_pos = None;
+                    }
+                    if window_state.show_hud {
+                        window_state.window.request_redraw();
+                    }
                 }
             }
             WindowEvent::CursorMoved { position, .. } => {
                 if let Some(window_state) = &mut self.window_state {
                     let position = Vec2::new(position.x, position.y);
-                    if window_state.mouse_down {
+                    if let Some(selection) = &mut window_state.selection {
+                        selection.end = position;
+                        window_state.window.request_redraw();
+                    } else if let Some(drag) = window_state.rotation {
+                        window_state.drag_rotation(drag, position);
+                    } else if window_state.mouse_down {
                         if let Some(prior) = window_state.prior_mouse_pos {
-                            window_state.transform =
-                                Affine::translate(position - prior) * window_state.transform;
-                            window_state.update_globals();
+                            let delta = position - prior;
+                            window_state.move_view(Change::Pan, Affine::translate(delta));
+                            window_state.motion.drag(delta, Instant::now());
                         }
                     }
                     window_state.prior_mouse_pos = Some(position);
+                    if window_state.show_hud {
+                        // for the coordinates under the cursor
+                        window_state.window.request_redraw();
+                    }
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                if let Some(window_state) = &mut self.window_state {
+                    window_state.modifiers = modifiers.state();
+                    if let Some(selection) = &mut window_state.selection {
+                        selection.zoom_out = window_state.modifiers.alt_key();
+                        window_state.window.request_redraw();
+                    }
                 }
             }
             WindowEvent::KeyboardInput { event, .. } => {
                 if let Some(window_state) = &mut self.window_state {
-                    if matches!(event.state, ElementState::Pressed) {
-                        match event.logical_key {
-                            winit::keyboard::Key::Named(NamedKey::Space) => {
-                                window_state.transform = Affine::IDENTITY;
-                                window_state.update_globals();
-                            }
-                            winit::keyboard::Key::Named(
-                                NamedKey::ArrowRight | NamedKey::ArrowLeft,
-                            ) => {
-                                if let Some(prior_position) = window_state.prior_mouse_pos {
-                                    let is_clockwise = event.logical_key == NamedKey::ArrowLeft;
-                                    let angle = if is_clockwise { -0.1 * TAU } else { 0.1 * TAU };
-                                    window_state.transform = Affine::translate(prior_position)
-                                        * Affine::rotate(angle)
-                                        * Affine::translate(-prior_position)
-                                        * window_state.transform;
-                                    window_state.update_globals();
-                                }
-                            }
-                            _ => (),
+                    if event.state == ElementState::Pressed {
+                        let chord = Chord::key(window_state.modifiers, event.logical_key);
+                        if let Some(action) = window_state.bindings.action(&chord) {
+                            window_state.perform(action);
                         }
                     }
                 }
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 if let Some(window_state) = &mut self.window_state {
-                    if button == MouseButton::Left {
+                    // right dragging or shift dragging zooms to a rectangle, alt zooms out
+                    let pressed = state == ElementState::Pressed;
+                    if pressed {
+                        let chord = Chord::mouse(window_state.modifiers, button);
+                        if let Some(action) = window_state.bindings.action(&chord) {
+                            window_state.perform(action);
+                        }
+                    }
+                    let selecting = match button {
+                        MouseButton::Right => true,
+                        // unless the left button was already panning when the selection started
+                        MouseButton::Left if pressed => window_state.modifiers.shift_key(),
+                        MouseButton::Left => {
+                            window_state.selection.is_some() && !window_state.mouse_down
+                        }
+                        _ => false,
+                    };
+                    if selecting {
+                        if pressed {
+                            window_state.start_selection();
+                        } else {
+                            window_state.finish_selection();
+                        }
+                    } else if button == MouseButton::Left {
+                        let now = Instant::now();
                         window_state.mouse_down = state == ElementState::Pressed;
+                        window_state
+                            .dynamic_resolution
+                            .set_held(window_state.mouse_down, now);
+                        if window_state.mouse_down {
+                            window_state.motion.grab();
+                            // every drag is a step of its own
+                            window_state.history.end_gesture();
+                            // dragging with ctrl held turns the view instead of moving it
+                            if window_state.modifiers.control_key() {
+                                window_state.start_rotation();
+                            }
+                        } else if window_state.rotation.take().is_some() {
+                            window_state.window.request_redraw();
+                        } else {
+                            // keeps panning at the speed the view was let go at
+                            window_state.motion.release(now);
+                            window_state.window.request_redraw();
+                        }
                     }
                 }
             }
+            WindowEvent::Touch(touch) => {
+                if let Some(window_state) = &mut self.window_state {
+                    window_state.touch(touch);
+                }
+            }
+            // trackpad gestures, which only some platforms report
+            WindowEvent::PinchGesture { delta, .. } => {
+                if let Some(window_state) = &mut self.window_state {
+                    let anchor = window_state.anchor();
+                    // the delta is roughly the relative change in scale, but a fast pinch can
+                    // report -1 or less, which would flatten or mirror the view
+                    let zoom = Affine::translate(anchor)
+                        * Affine::scale(delta.exp())
+                        * Affine::translate(-anchor);
+                    window_state.move_view(Change::Zoom, zoom);
+                }
+            }
+            WindowEvent::RotationGesture { delta, .. } => {
+                if let Some(window_state) = &mut self.window_state {
+                    // positive deltas turn counterclockwise
+                    window_state.rotate_by(-(delta as f64).to_radians());
+                }
+            }
             WindowEvent::MouseWheel { delta, .. } => {
-                const BASE: f64 = 1.05;
                 const PIXELS_PER_LINE: f64 = 20.0;
 
                 if let Some(window_state) = &mut self.window_state {
@@ -518,21 +1628,41 @@ impl ApplicationHandler for App {
                         } else {
                             0.0
                         };
-                        window_state.transform = Affine::translate(prior_position)
-                            * Affine::scale(BASE.powf(exponent))
-                            * Affine::translate(-prior_position)
-                            * window_state.transform;
-                        window_state.update_globals();
+                        let log_factor = exponent * window_state.bindings.wheel_zoom.ln();
+                        window_state.zoom_by(prior_position, log_factor);
                     }
                 }
             }
             _ => (),
         }
     }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // wake up to render at full resolution once input has gone idle
+        let idle_at = self
+            .window_state
+            .as_ref()
+            .and_then(|window_state| window_state.dynamic_resolution.idle_at());
+        match idle_at {
+            Some(idle_at) if idle_at <= Instant::now() => {
+                if let Some(window_state) = &self.window_state {
+                    window_state.window.request_redraw();
+                }
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            Some(idle_at) => event_loop.set_control_flow(ControlFlow::WaitUntil(idle_at)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub fn run() {
+    explore(None, Settings::default());
+}
+
+/// Open the window showing `view` with `settings`.
+pub fn explore(view: Option<View>, settings: Settings) {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -543,6 +1673,6 @@ pub fn run() {
     }
     let event_loop = EventLoop::with_user_event().build().unwrap();
 
-    let mut app = App::default();
+    let mut app = App::new(view, settings);
     event_loop.run_app(&mut app).unwrap();
 }
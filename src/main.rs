@@ -1,5 +1,12 @@
-use wgpu_mandelbrot::run;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
 
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    cli::run()
+}
+
+#[cfg(target_arch = "wasm32")]
 fn main() {
-    run();
+    wgpu_mandelbrot::run();
 }
@@ -0,0 +1,252 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::Path,
+};
+
+use kurbo::Vec2;
+
+use crate::{palette::Palette, settings::Settings, view::View, Globals};
+
+/// The iteration that is rendered, for telling images of other formulas apart.
+pub const FORMULA: &str = "z^2 + c";
+
+const CENTER: &str = "mandelbrot:center";
+const ZOOM: &str = "mandelbrot:zoom";
+const ROTATION: &str = "mandelbrot:rotation";
+const FORMULA_KEY: &str = "mandelbrot:formula";
+const PALETTE: &str = "mandelbrot:palette";
+const MAX_ITERATIONS: &str = "mandelbrot:max-iterations";
+const VERSION: &str = "mandelbrot:version";
+
+#[derive(Debug)]
+pub enum MetadataError {
+    Png(png::DecodingError),
+    /// The image doesn't have this key, most likely it wasn't exported by this app
+    Missing(&'static str),
+    Invalid {
+        key: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::Png(err) => write!(f, "failed to read the image: {err}"),
+            MetadataError::Missing(key) => write!(f, "the image has no {key} metadata"),
+            MetadataError::Invalid { key, value } => write!(f, "invalid {key} metadata: {value}"),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+impl From<png::DecodingError> for MetadataError {
+    fn from(err: png::DecodingError) -> Self {
+        MetadataError::Png(err)
+    }
+}
+
+/// Everything needed to get back to the view an image was exported from.
+///
+/// This is stored in text chunks of exported PNGs, one per field with keys starting with
+/// `mandelbrot:`. Numbers are written with as many digits as it takes to read back the exact same
+/// value, so a restored view is the exact view that was exported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metadata {
+    pub view: View,
+    pub formula: String,
+    pub palette: Palette,
+    pub max_iterations: u32,
+    /// Version of the app that exported the image
+    pub version: String,
+}
+
+impl Metadata {
+    /// The metadata of `view` rendered at `width` by `height` with `settings` by this version of
+    /// the app.
+    pub fn new(view: View, settings: &Settings, width: u32, height: u32) -> Self {
+        let globals = Globals::for_view(&view, settings, width, height);
+        Self {
+            view,
+            formula: FORMULA.to_string(),
+            palette: settings.palette,
+            max_iterations: globals.max_iterations(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// The metadata as keyword and text pairs.
+    pub fn to_text(&self) -> Vec<(&'static str, String)> {
+        let View {
+            center,
+            zoom,
+            rotation,
+        } = self.view;
+        vec![
+            ("Software", format!("wgpu-mandelbrot {}", self.version)),
+            (CENTER, format!("{} {}", center.x, center.y)),
+            (ZOOM, zoom.to_string()),
+            (ROTATION, rotation.to_string()),
+            (FORMULA_KEY, self.formula.clone()),
+            (PALETTE, self.palette.to_string()),
+            (MAX_ITERATIONS, self.max_iterations.to_string()),
+            (VERSION, self.version.clone()),
+        ]
+    }
+
+    /// Read the metadata back from keyword and text pairs, ignoring keys it doesn't know.
+    pub fn from_text<'a>(
+        text: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, MetadataError> {
+        let text: Vec<_> = text.into_iter().collect();
+        let get = |key: &'static str| {
+            text.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.trim())
+                .ok_or(MetadataError::Missing(key))
+        };
+        let invalid = |key: &'static str, value: &str| MetadataError::Invalid {
+            key,
+            value: value.to_string(),
+        };
+        let parse_f64 = |key: &'static str| {
+            let value = get(key)?;
+            value.parse::<f64>().map_err(|_| invalid(key, value))
+        };
+
+        let center = get(CENTER)?;
+        let (x, y) = center
+            .split_once(' ')
+            .and_then(|(x, y)| Some((x.parse().ok()?, y.trim().parse().ok()?)))
+            .ok_or_else(|| invalid(CENTER, center))?;
+        let palette = get(PALETTE)?;
+        let max_iterations = get(MAX_ITERATIONS)?;
+        Ok(Self {
+            view: View {
+                center: Vec2::new(x, y),
+                zoom: parse_f64(ZOOM)?,
+                rotation: parse_f64(ROTATION)?,
+            },
+            formula: get(FORMULA_KEY)?.to_string(),
+            palette: palette.parse().map_err(|_| invalid(PALETTE, palette))?,
+            max_iterations: max_iterations
+                .parse()
+                .map_err(|_| invalid(MAX_ITERATIONS, max_iterations))?,
+            version: get(VERSION)?.to_string(),
+        })
+    }
+
+    /// Add the metadata to a PNG as `tEXt` chunks before its header is written.
+    pub fn add_to_png<W: Write>(
+        &self,
+        encoder: &mut png::Encoder<W>,
+    ) -> Result<(), png::EncodingError> {
+        for (keyword, text) in self.to_text() {
+            encoder.add_text_chunk(keyword.to_string(), text)?;
+        }
+        Ok(())
+    }
+
+    /// Read the metadata from the `tEXt` or `iTXt` chunks of a PNG.
+    pub fn read_png(reader: impl Read) -> Result<Self, MetadataError> {
+        let reader = png::Decoder::new(reader).read_info()?;
+        let info = reader.info();
+        let mut text: Vec<(&str, String)> = info
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.clone()))
+            .collect();
+        for chunk in &info.utf8_text {
+            if let Ok(value) = chunk.get_text() {
+                text.push((chunk.keyword.as_str(), value));
+            }
+        }
+        Self::from_text(text.iter().map(|(k, v)| (*k, v.as_str())))
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, MetadataError> {
+        let file = File::open(path).map_err(|err| MetadataError::Png(err.into()))?;
+        Self::read_png(BufReader::new(file))
+    }
+
+    /// `settings` with the palette and iteration limit the image was rendered with.
+    pub fn apply(&self, settings: Settings) -> Settings {
+        Settings {
+            palette: self.palette,
+            max_iterations: Some(self.max_iterations),
+            ..settings
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{antialiasing::Antialiasing, image::Image};
+
+    #[test]
+    fn test_png_round_trip() {
+        let metadata = Metadata::new(
+            View {
+                center: Vec2::new(-0.743643887037151, 0.13182590420533),
+                zoom: 123456.789,
+                rotation: -0.1,
+            },
+            &Settings::default(),
+            640,
+            480,
+        );
+        let mut png = Vec::new();
+        Image::new(4, 3)
+            .write_png(&mut png, Some(&metadata))
+            .unwrap();
+        assert_eq!(Metadata::read_png(png.as_slice()).unwrap(), metadata);
+
+        let mut png = Vec::new();
+        Image::new(4, 3).write_png(&mut png, None).unwrap();
+        assert!(matches!(
+            Metadata::read_png(png.as_slice()),
+            Err(MetadataError::Missing(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_loaded_settings() {
+        let palette = Palette::default().shifted(0.25);
+        let rendered = Settings {
+            palette,
+            max_iterations: Some(777),
+            ..Settings::default()
+        };
+        let metadata = Metadata::new(View::new(Vec2::new(-0.75, 0.1), 20.0), &rendered, 64, 48);
+        let path = std::env::temp_dir().join(format!("metadata-test-{}.png", std::process::id()));
+        Image::new(4, 3)
+            .write_png(&mut File::create(&path).unwrap(), Some(&metadata))
+            .unwrap();
+        let loaded = Metadata::load_png(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let current = Settings {
+            antialiasing: Antialiasing::Jitter(8),
+            ..Settings::default()
+        };
+        let applied = loaded.unwrap().apply(current);
+        assert_eq!(applied.palette, palette);
+        assert_eq!(applied.max_iterations, Some(777));
+        // how the window smooths the image isn't part of it
+        assert_eq!(applied.antialiasing, Antialiasing::Jitter(8));
+    }
+
+    #[test]
+    fn test_max_iterations_matches_render() {
+        let metadata = Metadata::new(View::default(), &Settings::default(), 300, 200);
+        // the whole set is 300 pixels wide, so 100 per unit
+        assert_eq!(
+            metadata.max_iterations,
+            (100.0 * (100.0 / 2f32.sqrt()).log2()) as u32
+        );
+    }
+}
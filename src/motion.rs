@@ -0,0 +1,337 @@
+//! Movement of the window's view that carries on between input events.
+//!
+//! Input only sets a [`Motion`] going, every frame then advances it by the time since the frame
+//! before with [`Motion::step`], so it plays out the same at any frame rate and keeps going while
+//! no events arrive.
+
+use std::{collections::VecDeque, f64::consts::PI};
+
+use kurbo::{Affine, Vec2};
+use web_time::{Duration, Instant};
+
+use crate::{animation::Easing, view::View};
+
+/// How quickly panning slows down after the button is released, speed drops by a factor of e
+/// every `1 / FRICTION` seconds
+const FRICTION: f64 = 4.0;
+/// Speed in pixels per second below which panning stops
+const MIN_SPEED: f64 = 10.0;
+/// How far back drag movement counts towards the speed the view keeps after release
+const VELOCITY_WINDOW: Duration = Duration::from_millis(80);
+/// Time a zoom or pan step takes to get within a factor of e of its target
+const STEP_TIME_CONSTANT: f64 = 0.06;
+/// Log of the zoom factor a zoom step is snapped to its target from
+const ZOOM_EPSILON: f64 = 1e-3;
+/// Distance in pixels a pan step is snapped to its target from
+const PAN_EPSILON: f64 = 0.5;
+/// Length of a transition between views at the same zoom level
+const TRANSITION_TIME: f64 = 0.5;
+/// Length a transition gets longer by for every factor of e it zooms in or out
+const TRANSITION_TIME_PER_ZOOM: f64 = 0.1;
+/// Longest a transition takes, no matter how far it zooms
+const MAX_TRANSITION_TIME: f64 = 3.0;
+
+/// An eased flight from one view to another.
+#[derive(Clone, Copy, Debug)]
+struct Transition {
+    from: View,
+    to: View,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Transition {
+    fn new(from: View, mut to: View, start: Instant) -> Self {
+        // turn the short way around
+        let turn = (to.rotation - from.rotation + PI).rem_euclid(2.0 * PI) - PI;
+        to.rotation = from.rotation + turn;
+        let seconds = TRANSITION_TIME + TRANSITION_TIME_PER_ZOOM * (to.zoom / from.zoom).ln().abs();
+        Self {
+            from,
+            to,
+            start,
+            duration: Duration::from_secs_f64(seconds.min(MAX_TRANSITION_TIME)),
+        }
+    }
+
+    /// How far along the transition is at `now`, from 0 to 1.
+    fn progress(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.start);
+        (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+}
+
+/// Kinetic panning, smooth zoom steps and transitions between views, in window pixels.
+#[derive(Clone, Debug, Default)]
+pub struct Motion {
+    /// Speed of panning in pixels per second
+    velocity: Vec2,
+    /// Movement of the current drag within the last [`VELOCITY_WINDOW`] and when it happened
+    drag: VecDeque<(Instant, Vec2)>,
+    /// The point zoom steps zoom about and the log of the zoom factor that is still to come
+    zoom: Option<(Vec2, f64)>,
+    /// The distance pan steps still have to move the view
+    pan: Option<Vec2>,
+    transition: Option<Transition>,
+    /// When the motion was last advanced
+    last_step: Option<Instant>,
+}
+
+impl Motion {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether there is movement left for [`Motion::step`] to apply.
+    pub fn is_moving(&self) -> bool {
+        self.velocity != Vec2::ZERO
+            || self.zoom.is_some()
+            || self.pan.is_some()
+            || self.transition.is_some()
+    }
+
+    /// Stop panning, and transitions, since the view was grabbed.
+    pub fn grab(&mut self) {
+        self.velocity = Vec2::ZERO;
+        self.drag.clear();
+        self.transition = None;
+    }
+
+    /// Record the view being dragged by `delta` at `now`, which the caller already applied.
+    pub fn drag(&mut self, delta: Vec2, now: Instant) {
+        self.transition = None;
+        self.drag.push_back((now, delta));
+        while self
+            .drag
+            .front()
+            .is_some_and(|&(time, _)| time + VELOCITY_WINDOW <= now)
+        {
+            self.drag.pop_front();
+        }
+    }
+
+    /// Let go of the view at `now`, which keeps the speed it was dragged at just before.
+    pub fn release(&mut self, now: Instant) {
+        let recent: Vec2 = self
+            .drag
+            .drain(..)
+            .filter(|&(time, _)| time + VELOCITY_WINDOW > now)
+            .fold(Vec2::ZERO, |sum, (_, delta)| sum + delta);
+        let velocity = recent / VELOCITY_WINDOW.as_secs_f64();
+        if velocity.hypot() >= MIN_SPEED {
+            self.start(now);
+            self.velocity = velocity;
+        }
+    }
+
+    /// Zoom by a factor of `e^log_factor` about `anchor`, in steps over the next few frames.
+    pub fn zoom_by(&mut self, anchor: Vec2, log_factor: f64, now: Instant) {
+        self.start(now);
+        self.transition = None;
+        let pending = self.zoom.map_or(0.0, |(_, pending)| pending);
+        self.zoom = Some((anchor, pending + log_factor));
+    }
+
+    /// Move the view by `delta` pixels, in steps over the next few frames.
+    pub fn pan_by(&mut self, delta: Vec2, now: Instant) {
+        self.start(now);
+        self.transition = None;
+        self.pan = Some(self.pan.unwrap_or(Vec2::ZERO) + delta);
+    }
+
+    /// The view a transition in flight ends on.
+    pub fn target(&self) -> Option<View> {
+        self.transition.map(|transition| transition.to)
+    }
+
+    /// Fly from view `from` to view `to`, replacing any other motion.
+    pub fn transition(&mut self, from: View, to: View, now: Instant) {
+        self.velocity = Vec2::ZERO;
+        self.drag.clear();
+        self.zoom = None;
+        self.pan = None;
+        self.last_step = Some(now);
+        self.transition = Some(Transition::new(from, to, now));
+    }
+
+    /// Start the clock if nothing is moving yet, so the first step isn't measured from whenever
+    /// the last motion ended.
+    fn start(&mut self, now: Instant) {
+        if !self.is_moving() {
+            self.last_step = Some(now);
+        }
+    }
+
+    /// Advance the motion to `now` and return where it moves `transform` of a window of size
+    /// `viewport`, or `None` if nothing is moving.
+    pub fn step(&mut self, transform: Affine, viewport: Vec2, now: Instant) -> Option<Affine> {
+        if !self.is_moving() {
+            return None;
+        }
+        let dt = self.last_step.map_or(0.0, |last_step| {
+            now.saturating_duration_since(last_step).as_secs_f64()
+        });
+        self.last_step = Some(now);
+
+        if let Some(transition) = self.transition {
+            let progress = transition.progress(now);
+            if progress >= 1.0 {
+                self.transition = None;
+            }
+            let t = Easing::EaseInOut.apply(progress);
+            return Some(
+                transition
+                    .from
+                    .lerp(&transition.to, t)
+                    .to_transform(viewport),
+            );
+        }
+
+        let mut transform = transform;
+        if self.velocity != Vec2::ZERO {
+            // the exact integral of the decaying speed over the step
+            let decay = (-FRICTION * dt).exp();
+            transform = Affine::translate(self.velocity * (1.0 - decay) / FRICTION) * transform;
+            self.velocity *= decay;
+            if self.velocity.hypot() < MIN_SPEED {
+                self.velocity = Vec2::ZERO;
+            }
+        }
+        if let Some(pending) = self.pan {
+            let remaining = pending * (-dt / STEP_TIME_CONSTANT).exp();
+            let applied = if remaining.hypot() < PAN_EPSILON {
+                self.pan = None;
+                pending
+            } else {
+                self.pan = Some(remaining);
+                pending - remaining
+            };
+            transform = Affine::translate(applied) * transform;
+        }
+        if let Some((anchor, pending)) = self.zoom {
+            let remaining = pending * (-dt / STEP_TIME_CONSTANT).exp();
+            let applied = if remaining.abs() < ZOOM_EPSILON {
+                self.zoom = None;
+                pending
+            } else {
+                self.zoom = Some((anchor, remaining));
+                pending - remaining
+            };
+            transform = Affine::translate(anchor)
+                * Affine::scale(applied.exp())
+                * Affine::translate(-anchor)
+                * transform;
+        }
+        Some(transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Step `motion` at `frame_rate` until it stops, returning the transform it ends at.
+    fn settle(motion: &mut Motion, start: Instant, frame_rate: f64) -> Affine {
+        let viewport = Vec2::new(800.0, 600.0);
+        let mut transform = Affine::IDENTITY;
+        let mut frame = 1;
+        while let Some(next) = motion.step(
+            transform,
+            viewport,
+            start + Duration::from_secs_f64(frame as f64 / frame_rate),
+        ) {
+            transform = next;
+            frame += 1;
+            assert!(frame < 10_000, "motion never stops");
+        }
+        transform
+    }
+
+    fn drag_at(speed: f64, start: Instant) -> Motion {
+        let mut motion = Motion::new();
+        motion.grab();
+        for i in 1..=10 {
+            let delta = Vec2::new(speed / 100.0, 0.0);
+            motion.drag(delta, start + Duration::from_millis(10 * i));
+        }
+        motion
+    }
+
+    #[test]
+    fn test_pan_coasts_to_a_stop() {
+        let start = Instant::now();
+        let released = start + Duration::from_millis(100);
+        let mut motion = drag_at(1000.0, start);
+        motion.release(released);
+        assert!(motion.is_moving());
+        let slow = settle(&mut motion.clone(), released, 30.0);
+        let fast = settle(&mut motion, released, 144.0);
+        // the whole distance is the speed over the friction, less what was left under MIN_SPEED
+        let [.., x, y] = fast.as_coeffs();
+        assert!((x - 1000.0 / FRICTION).abs() < MIN_SPEED / FRICTION, "{x}");
+        assert_eq!(y, 0.0);
+        // and doesn't depend on the frame rate beyond when exactly that happens
+        assert!((slow.as_coeffs()[4] - x).abs() < MIN_SPEED / FRICTION);
+    }
+
+    #[test]
+    fn test_release_after_holding_still() {
+        let start = Instant::now();
+        let mut motion = drag_at(1000.0, start);
+        motion.release(start + Duration::from_millis(500));
+        assert!(!motion.is_moving());
+    }
+
+    #[test]
+    fn test_zoom_steps_reach_target() {
+        let start = Instant::now();
+        let anchor = Vec2::new(200.0, 100.0);
+        let mut motion = Motion::new();
+        motion.zoom_by(anchor, 2f64.ln(), start);
+        motion.zoom_by(anchor, 2f64.ln(), start);
+        let transform = settle(&mut motion, start, 60.0);
+        let expected = Affine::translate(anchor) * Affine::scale(4.0) * Affine::translate(-anchor);
+        for (a, b) in transform.as_coeffs().iter().zip(expected.as_coeffs()) {
+            assert!((a - b).abs() < 1e-9, "{transform:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_pan_steps_reach_target() {
+        let start = Instant::now();
+        let mut motion = Motion::new();
+        motion.pan_by(Vec2::new(80.0, 0.0), start);
+        motion.pan_by(Vec2::new(0.0, -60.0), start);
+        let transform = settle(&mut motion, start, 60.0);
+        let [a, b, c, d, x, y] = transform.as_coeffs();
+        assert_eq!([a, b, c, d], [1.0, 0.0, 0.0, 1.0]);
+        assert!(
+            (x - 80.0).abs() < 1e-9 && (y + 60.0).abs() < 1e-9,
+            "{transform:?}"
+        );
+    }
+
+    #[test]
+    fn test_transition_ends_on_view() {
+        let start = Instant::now();
+        let viewport = Vec2::new(800.0, 600.0);
+        let from = View::default();
+        let to = View {
+            center: Vec2::new(-0.7453, 0.1127),
+            zoom: 1000.0,
+            rotation: 2.0 * PI - 0.1,
+        };
+        let mut motion = Motion::new();
+        motion.transition(from, to, start);
+        let transform = settle(&mut motion, start, 60.0);
+        let view = View::from_transform(transform, viewport);
+        assert!((view.center - to.center).hypot() < 1e-9);
+        assert!((view.zoom / to.zoom - 1.0).abs() < 1e-9);
+        assert!((view.rotation + 0.1).abs() < 1e-9);
+
+        // input takes over from a transition
+        motion.transition(from, to, start);
+        motion.zoom_by(Vec2::ZERO, 1.0, start);
+        assert!(motion.transition.is_none());
+    }
+}
@@ -0,0 +1,83 @@
+use kurbo::Vec2;
+
+/// How close the last point of an orbit has to come back to an earlier one to count as a cycle.
+const PERIOD_TOLERANCE: f64 = 1e-9;
+
+/// What happens to `z` under iteration for a single point `c`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Orbit {
+    /// Number of iterations before `|z|` grew beyond 2, like the shader counts them
+    pub escape_time: Option<u32>,
+    /// Length of the cycle the orbit settled into, for points that didn't escape
+    pub period: Option<u32>,
+    /// Every value of `z`, starting with 0
+    pub points: Vec<Vec2>,
+}
+
+impl Orbit {
+    /// Iterate `c` in double precision up to `max_iterations` times.
+    pub fn new(c: Vec2, max_iterations: u32) -> Self {
+        let mut z = Vec2::ZERO;
+        let mut points = vec![z];
+        let mut escape_time = None;
+        for i in 0..max_iterations {
+            if z.hypot2() > 4.0 {
+                escape_time = Some(i);
+                break;
+            }
+            z = Vec2::new(z.x * z.x - z.y * z.y + c.x, 2.0 * z.x * z.y + c.y);
+            points.push(z);
+        }
+        if escape_time.is_none() && z.hypot2() > 4.0 {
+            escape_time = Some(max_iterations);
+        }
+        let period = escape_time
+            .is_none()
+            .then(|| Self::find_period(&points))
+            .flatten();
+        Self {
+            escape_time,
+            period,
+            points,
+        }
+    }
+
+    /// The smallest number of steps back from the end of `points` that leads to the same point.
+    fn find_period(points: &[Vec2]) -> Option<u32> {
+        let (last, earlier) = points.split_last()?;
+        earlier
+            .iter()
+            .rev()
+            .position(|point| (*point - *last).hypot() < PERIOD_TOLERANCE)
+            .map(|steps| steps as u32 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_time() {
+        let orbit = Orbit::new(Vec2::new(1.0, 0.0), 100);
+        assert_eq!(orbit.escape_time, Some(3));
+        assert_eq!(orbit.period, None);
+        assert_eq!(
+            orbit.points,
+            [0.0, 1.0, 2.0, 5.0].map(|x| Vec2::new(x, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_period() {
+        assert_eq!(Orbit::new(Vec2::ZERO, 1000).period, Some(1));
+        assert_eq!(Orbit::new(Vec2::new(-1.0, 0.0), 1000).period, Some(2));
+        // the center of the period 3 minibrot on the real axis
+        assert_eq!(
+            Orbit::new(Vec2::new(-1.7548776662, 0.0), 1000).period,
+            Some(3)
+        );
+        // a point on the boundary converges too slowly to find its cycle
+        assert_eq!(Orbit::new(Vec2::new(0.25, 0.0), 1000).period, None);
+    }
+}
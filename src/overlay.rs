@@ -0,0 +1,350 @@
+use kurbo::Vec2;
+use wgpu::{util::DeviceExt as _, BindGroup, BindGroupLayout, Device, Queue, TextureFormat};
+
+use crate::font;
+
+/// Most character cells a single call to [`Overlay::text`] draws, the rest are left out
+const MAX_CELLS: usize = 4096;
+
+/// Blends over the target while keeping its alpha, so the window stays opaque
+const BLEND: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent::OVER,
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct RectangleUniform {
+    bounds: [f32; 4],
+    fill: [f32; 4],
+    border: [f32; 4],
+    border_width: f32,
+    _padding: [f32; 3], // Padding to ensure 16-byte alignment
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextUniform {
+    color: [f32; 4],
+    background: [f32; 4],
+    origin: [f32; 2],
+    viewport: [f32; 2],
+    scale: f32,
+    _padding: [f32; 3], // Padding to ensure 16-byte alignment
+    font: [[u32; 4]; 48],
+}
+
+/// A character cell of text, drawn as an instance of a quad.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Cell {
+    /// Column and row
+    position: [u32; 2],
+    glyph: u32,
+}
+
+impl Cell {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Uint32x2, 1 => Uint32];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+
+    /// The cells of `lines`, padded with a space all around so their background forms a panel.
+    fn layout(lines: &[String]) -> Vec<Cell> {
+        let width = lines.iter().map(|it| it.chars().count()).max().unwrap_or(0) + 2;
+        let mut cells = Vec::new();
+        for (row, line) in lines.iter().enumerate() {
+            let mut chars = line.chars();
+            for column in 0..width {
+                let c = if column == 0 {
+                    ' '
+                } else {
+                    chars.next().unwrap_or(' ')
+                };
+                cells.push(Cell {
+                    position: [column as u32, row as u32],
+                    glyph: font::glyph_index(c),
+                });
+            }
+        }
+        cells.truncate(MAX_CELLS);
+        cells
+    }
+}
+
+/// Colors of a rectangle drawn by an [`Overlay`], with straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct RectangleStyle {
+    pub(crate) fill: [f32; 4],
+    pub(crate) border: [f32; 4],
+    pub(crate) border_width: f32,
+}
+
+/// How text drawn by an [`Overlay`] looks, with straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TextStyle {
+    pub(crate) color: [f32; 4],
+    /// Fills the cells behind the text
+    pub(crate) background: [f32; 4],
+}
+
+/// Draws interface elements over the presented image.
+///
+/// Everything is blended over the image that is already in the target, without touching its
+/// alpha so the window stays opaque.
+pub(crate) struct Overlay {
+    rectangle_pipeline: wgpu::RenderPipeline,
+    rectangle_buffer: wgpu::Buffer,
+    rectangle_bind_group: BindGroup,
+    text_pipeline: wgpu::RenderPipeline,
+    text_buffer: wgpu::Buffer,
+    text_bind_group: BindGroup,
+    /// The character cells of the text, in as many instances as there are
+    cell_buffer: wgpu::Buffer,
+}
+
+impl Overlay {
+    pub(crate) fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("overlay.wgsl"));
+
+        let bind_group_layout = uniform_layout(device, wgpu::ShaderStages::FRAGMENT);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let rectangle_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Rectangle Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_rectangle",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(BLEND),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let rectangle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rectangle Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[RectangleUniform {
+                bounds: [0.0; 4],
+                fill: [0.0; 4],
+                border: [0.0; 4],
+                border_width: 0.0,
+                _padding: [0.0; 3],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let rectangle_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Rectangle Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: rectangle_buffer.as_entire_binding(),
+            }],
+        });
+
+        let text_shader = device.create_shader_module(wgpu::include_wgsl!("text.wgsl"));
+        let text_layout = uniform_layout(device, wgpu::ShaderStages::VERTEX_FRAGMENT);
+        let text_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&text_layout],
+            push_constant_ranges: &[],
+        });
+        let text_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&text_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &text_shader,
+                entry_point: "vs_text",
+                buffers: &[Cell::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &text_shader,
+                entry_point: "fs_text",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(BLEND),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let text_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[TextUniform {
+                color: [0.0; 4],
+                background: [0.0; 4],
+                origin: [0.0; 2],
+                viewport: [1.0; 2],
+                scale: 1.0,
+                _padding: [0.0; 3],
+                font: font::packed(),
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let text_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Text Bind Group"),
+            layout: &text_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: text_buffer.as_entire_binding(),
+            }],
+        });
+        let cell_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Cell Buffer"),
+            size: (MAX_CELLS * std::mem::size_of::<Cell>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            rectangle_pipeline,
+            rectangle_buffer,
+            rectangle_bind_group,
+            text_pipeline,
+            text_buffer,
+            text_bind_group,
+            cell_buffer,
+        }
+    }
+
+    /// Record drawing the rectangle from `min` to `max`, in pixels, over `target`.
+    ///
+    /// Only one rectangle can be drawn per submission since they share a uniform buffer.
+    pub(crate) fn rectangle(
+        &self,
+        queue: &Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        (min, max): (Vec2, Vec2),
+        style: RectangleStyle,
+    ) {
+        let uniform = RectangleUniform {
+            bounds: [min.x as f32, min.y as f32, max.x as f32, max.y as f32],
+            fill: style.fill,
+            border: style.border,
+            border_width: style.border_width,
+            _padding: [0.0; 3],
+        };
+        queue.write_buffer(&self.rectangle_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let mut render_pass = begin_pass(encoder, target);
+        render_pass.set_pipeline(&self.rectangle_pipeline);
+        render_pass.set_bind_group(0, &self.rectangle_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Record drawing `lines` of text over `target` of size `viewport`, with the top left corner
+    /// of their panel at `origin` and `scale` pixels to every pixel of the font.
+    ///
+    /// Only one block of text can be drawn per submission since they share their buffers.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn text(
+        &self,
+        queue: &Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        viewport: Vec2,
+        origin: Vec2,
+        scale: f32,
+        lines: &[String],
+        style: TextStyle,
+    ) {
+        let cells = Cell::layout(lines);
+        if cells.is_empty() {
+            return;
+        }
+        // the font never changes, so it is left out of the write
+        let uniform = TextUniform {
+            color: style.color,
+            background: style.background,
+            origin: [origin.x as f32, origin.y as f32],
+            viewport: [viewport.x as f32, viewport.y as f32],
+            scale,
+            _padding: [0.0; 3],
+            font: [[0; 4]; 48],
+        };
+        let font_offset = std::mem::offset_of!(TextUniform, font);
+        queue.write_buffer(
+            &self.text_buffer,
+            0,
+            &bytemuck::bytes_of(&uniform)[..font_offset],
+        );
+        queue.write_buffer(&self.cell_buffer, 0, bytemuck::cast_slice(&cells));
+
+        let mut render_pass = begin_pass(encoder, target);
+        render_pass.set_pipeline(&self.text_pipeline);
+        render_pass.set_bind_group(0, &self.text_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.cell_buffer.slice(..));
+        render_pass.draw(0..6, 0..cells.len() as u32);
+    }
+}
+
+/// A layout with a single uniform buffer, visible to the shader `visibility`.
+fn uniform_layout(device: &Device, visibility: wgpu::ShaderStages) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Overlay Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
+
+/// Begin a render pass that draws over what is already in `target`.
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    target: &'a wgpu::TextureView,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Overlay Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,33 @@
+struct Rectangle {
+    // top left and bottom right corner in pixels
+    bounds: vec4<f32>,
+    fill: vec4<f32>,
+    border: vec4<f32>,
+    border_width: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> rectangle: Rectangle;
+
+// A single triangle that covers the whole target, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_rectangle(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let min_corner = rectangle.bounds.xy;
+    let max_corner = rectangle.bounds.zw;
+    if (any(pos.xy < min_corner) || any(pos.xy > max_corner)) {
+        discard;
+    }
+    // distance to the nearest edge
+    let inset = min(pos.xy - min_corner, max_corner - pos.xy);
+    if (min(inset.x, inset.y) < rectangle.border_width) {
+        return rectangle.border;
+    }
+    return rectangle.fill;
+}
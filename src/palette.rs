@@ -0,0 +1,154 @@
+use std::{fmt, str::FromStr};
+
+/// Maps normalized iteration counts to colors with a cosine per channel.
+///
+/// Every channel is `offset + amplitude * cos(frequency * t + phase)`, where `t` is the iteration
+/// count as a fraction of the iteration limit. The default is the palette `shader.wgsl` uses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub offset: [f32; 3],
+    pub amplitude: [f32; 3],
+    pub frequency: f32,
+    pub phase: [f32; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            offset: [0.5; 3],
+            amplitude: [0.5; 3],
+            frequency: std::f32::consts::TAU,
+            phase: [3.0, 5.0, 7.0],
+        }
+    }
+}
+
+impl Palette {
+    /// The linear RGB color for the normalized iteration count `t`.
+    pub fn color(&self, t: f32) -> [f32; 3] {
+        std::array::from_fn(|i| {
+            self.offset[i] + self.amplitude[i] * (self.frequency * t + self.phase[i]).cos()
+        })
+    }
+
+    /// The palette moved along by `amount`, so that its color for `t` is this one's for
+    /// `t + amount`. Animating `amount` cycles the colors.
+    pub fn shifted(self, amount: f32) -> Self {
+        Self {
+            phase: self.phase.map(|phase| phase + self.frequency * amount),
+            ..self
+        }
+    }
+}
+
+/// Written as `offset R G B; amplitude R G B; frequency F; phase R G B`, which is also what
+/// [`Palette::from_str`] reads.
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.offset;
+        write!(f, "offset {r} {g} {b}; ")?;
+        let [r, g, b] = self.amplitude;
+        write!(f, "amplitude {r} {g} {b}; ")?;
+        write!(f, "frequency {}; ", self.frequency)?;
+        let [r, g, b] = self.phase;
+        write!(f, "phase {r} {g} {b}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePaletteError(String);
+
+impl fmt::Display for ParsePaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid palette: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParsePaletteError {}
+
+impl FromStr for Palette {
+    type Err = ParsePaletteError;
+
+    /// Parse a palette written by its `Display` implementation. Parameters that are left out keep
+    /// their default value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |message: String| ParsePaletteError(message);
+        let mut palette = Palette::default();
+        for parameter in s.split(';').map(str::trim).filter(|it| !it.is_empty()) {
+            let mut words = parameter.split_whitespace();
+            let name = words.next().unwrap_or_default();
+            let values = words
+                .map(|it| {
+                    it.parse::<f32>()
+                        .map_err(|err| error(format!("{it}: {err}")))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let rgb = || -> Result<[f32; 3], ParsePaletteError> {
+                values
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| error(format!("{name} needs three values")))
+            };
+            match name {
+                "offset" => palette.offset = rgb()?,
+                "amplitude" => palette.amplitude = rgb()?,
+                "phase" => palette.phase = rgb()?,
+                "frequency" => match values.as_slice() {
+                    [frequency] => palette.frequency = *frequency,
+                    _ => return Err(error("frequency needs one value".to_string())),
+                },
+                _ => return Err(error(format!("unknown parameter {name}"))),
+            }
+        }
+        Ok(palette)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_matches_shader() {
+        let palette = Palette::default();
+        for t in [0.0, 0.1, 0.5, 0.93, 1.0] {
+            let shader = [0.0, 2.0, 4.0]
+                .map(|phase: f32| 0.5 + 0.5 * (3.0 + std::f32::consts::TAU * t + phase).cos());
+            for (a, b) in palette.color(t).into_iter().zip(shader) {
+                assert!((a - b).abs() < 1e-4, "{t}: {a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_shifted() {
+        let palette = Palette::default();
+        for (a, b) in palette
+            .shifted(0.3)
+            .color(0.2)
+            .into_iter()
+            .zip(palette.color(0.5))
+        {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let palette = Palette {
+            frequency: 12.5,
+            phase: [0.0, 0.1, -3.0],
+            ..Palette::default()
+        };
+        assert_eq!(palette.to_string().parse(), Ok(palette));
+        assert_eq!(
+            "frequency 2".parse(),
+            Ok(Palette {
+                frequency: 2.0,
+                ..Palette::default()
+            })
+        );
+        assert!("phase 1 2".parse::<Palette>().is_err());
+        assert!("hue 1".parse::<Palette>().is_err());
+    }
+}
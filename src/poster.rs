@@ -0,0 +1,187 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use kurbo::{Affine, Vec2};
+
+use crate::{
+    headless::{HeadlessError, Renderer},
+    metadata::Metadata,
+    settings::Settings,
+    tiles::{self, Tile},
+    transforms::general_transform,
+    view::View,
+    Globals,
+};
+
+#[derive(Debug)]
+pub enum PosterError {
+    Render(HeadlessError),
+    Png(png::EncodingError),
+    Io(io::Error),
+}
+
+impl fmt::Display for PosterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PosterError::Render(err) => write!(f, "failed to render a tile: {err}"),
+            PosterError::Png(err) => write!(f, "failed to encode the image: {err}"),
+            PosterError::Io(err) => write!(f, "failed to write the image: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for PosterError {}
+
+impl From<HeadlessError> for PosterError {
+    fn from(err: HeadlessError) -> Self {
+        PosterError::Render(err)
+    }
+}
+
+impl From<png::EncodingError> for PosterError {
+    fn from(err: png::EncodingError) -> Self {
+        PosterError::Png(err)
+    }
+}
+
+impl From<io::Error> for PosterError {
+    fn from(err: io::Error) -> Self {
+        PosterError::Io(err)
+    }
+}
+
+/// An image too large to render in one go.
+///
+/// The poster is rendered in tiles small enough for a texture, one band of tiles at a time, and
+/// every band is written out before the next one is rendered. Memory use is bounded by one band of
+/// `width` by `tile_size` pixels no matter how tall the poster is.
+///
+/// Each tile gets its own transform onto the complex plane rather than being cut out of one shared
+/// transform, so the offsets the shader works with stay as small as a tile and precision doesn't
+/// get worse further into the poster.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Poster {
+    pub view: View,
+    pub width: u32,
+    pub height: u32,
+    pub settings: Settings,
+    /// Largest side length of a tile, lowered further to what the renderer supports
+    pub tile_size: u32,
+}
+
+impl Poster {
+    pub const DEFAULT_TILE_SIZE: u32 = 2048;
+
+    pub fn new(view: View, width: u32, height: u32) -> Self {
+        Self {
+            view,
+            width,
+            height,
+            settings: Settings::default(),
+            tile_size: Self::DEFAULT_TILE_SIZE,
+        }
+    }
+
+    /// The transform from pixels of `tile` to the complex plane.
+    pub fn tile_transform(&self, tile: Tile) -> Affine {
+        let viewport = Vec2::new(self.width as f64, self.height as f64);
+        let origin = Vec2::new(tile.x as f64, tile.y as f64);
+        let size = Vec2::new(tile.width as f64, tile.height as f64);
+        let tile_to_poster = general_transform(Vec2::ZERO, size, origin, origin + size);
+        self.view.pixel_to_complex(viewport) * tile_to_poster
+    }
+
+    /// Render the poster and encode it as a PNG into `writer`.
+    pub fn write_png(&self, renderer: &Renderer, writer: impl Write) -> Result<(), PosterError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        Metadata::new(self.view, &self.settings, self.width, self.height)
+            .add_to_png(&mut encoder)?;
+        let mut writer = encoder.write_header()?;
+        let mut stream = writer.stream_writer()?;
+
+        let tile_size = self.tile_size.min(renderer.max_size()).max(1);
+        let row_length = self.width as usize * 4;
+        for band_y in (0..self.height).step_by(tile_size as usize) {
+            let band = Tile {
+                x: 0,
+                y: band_y,
+                width: self.width,
+                height: tile_size.min(self.height - band_y),
+            };
+            log::info!("rendering rows {band_y} to {}", band_y + band.height);
+            let mut pixels = vec![0; row_length * band.height as usize];
+            for tile in tiles::split_region(band, tile_size) {
+                let globals = Globals {
+                    samples: self.settings.antialiasing.samples(),
+                    ..Globals::for_viewport(self.tile_transform(tile), tile.width, tile.height)
+                        .with_settings(&self.settings)
+                };
+                let image = renderer.render_globals(globals, tile.width, tile.height)?;
+                let tile_row_length = tile.width as usize * 4;
+                for (y, row) in image.pixels.chunks_exact(tile_row_length).enumerate() {
+                    let start = y * row_length + tile.x as usize * 4;
+                    pixels[start..start + tile_row_length].copy_from_slice(row);
+                }
+            }
+            stream.write_all(&pixels)?;
+        }
+        stream.finish()?;
+        Ok(())
+    }
+
+    pub fn save_png(&self, renderer: &Renderer, path: impl AsRef<Path>) -> Result<(), PosterError> {
+        self.write_png(renderer, BufWriter::new(File::create(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{antialiasing::Antialiasing, image::Image};
+
+    #[test]
+    fn test_tile_transform_matches_poster() {
+        let poster = Poster::new(View::new(Vec2::new(-0.75, 0.1), 30.0), 1000, 700);
+        let poster_to_complex = poster.view.pixel_to_complex(Vec2::new(1000.0, 700.0));
+        let tile = Tile {
+            x: 512,
+            y: 256,
+            width: 256,
+            height: 128,
+        };
+        for (x, y) in [(0.0, 0.0), (255.5, 0.5), (100.0, 127.0)] {
+            let in_tile = poster.tile_transform(tile) * kurbo::Point::new(x, y);
+            let in_poster = poster_to_complex * kurbo::Point::new(x + 512.0, y + 256.0);
+            assert!((in_tile - in_poster).hypot() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_write_png_streams_tiles() {
+        let poster = Poster {
+            settings: Settings {
+                antialiasing: Antialiasing::Supersample(2),
+                ..Settings::default()
+            },
+            tile_size: 16,
+            ..Poster::new(View::default(), 50, 37)
+        };
+        let mut png = Vec::new();
+        poster.write_png(&Renderer::Cpu, &mut png).unwrap();
+        let image = Image::read_png(png.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (50, 37));
+        assert!(image.pixels.chunks_exact(4).all(|pixel| pixel[3] == 255));
+        // the inside of the set is filled in, not left over from an unrendered tile
+        assert_eq!(image.pixel(25, 18), image.pixel(24, 17));
+        assert_eq!(
+            Metadata::read_png(png.as_slice()).unwrap().view,
+            poster.view
+        );
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::VecDeque;
+
+use web_time::{Duration, Instant};
+
+use crate::{
+    antialiasing::Antialiasing,
+    tiles::{self, Tile},
+};
+
+/// Side length in pixels of the tiles each refinement pass is split into.
+pub const TILE_SIZE: u32 = 128;
+
+/// One pass of the progressive refinement schedule.
+///
+/// Each pass renders the whole view at `1 / downscale` of the window resolution and with
+/// `iteration_scale` of the full iteration budget. Coarse passes are cheap, so something shows up
+/// on screen right away, and every following pass replaces the previous one with a sharper and
+/// more accurate image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Level {
+    pub downscale: u32,
+    pub iteration_scale: f32,
+}
+
+/// The levels that are rendered, in order, after every change of the view.
+pub const LEVELS: [Level; 4] = [
+    Level {
+        downscale: 8,
+        iteration_scale: 0.25,
+    },
+    Level {
+        downscale: 4,
+        iteration_scale: 0.5,
+    },
+    Level {
+        downscale: 2,
+        iteration_scale: 0.75,
+    },
+    Level {
+        downscale: 1,
+        iteration_scale: 1.0,
+    },
+];
+
+impl Level {
+    /// The size in pixels that this level renders at for a window of `width` by `height`.
+    ///
+    /// Partial blocks at the right and bottom edges still get a pixel so the level always covers
+    /// the whole window.
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        (
+            width.div_ceil(self.downscale).max(1),
+            height.div_ceil(self.downscale).max(1),
+        )
+    }
+}
+
+/// A step of the refinement schedule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pass {
+    /// Render the view at one of the [`LEVELS`].
+    Level(Level),
+    /// Resample the pixels that sit on an edge between different iteration counts.
+    Adaptive,
+    /// Blend another jittered sample of every pixel into the accumulated image.
+    Jitter { sample: u32 },
+}
+
+impl Pass {
+    /// The size in pixels that this pass renders at for a window of `width` by `height`.
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Pass::Level(level) => level.size(width, height),
+            Pass::Adaptive | Pass::Jitter { .. } => (width, height),
+        }
+    }
+
+    /// The fraction of the full iteration budget this pass spends.
+    pub fn iteration_scale(&self) -> f32 {
+        match self {
+            Pass::Level(level) => level.iteration_scale,
+            Pass::Adaptive | Pass::Jitter { .. } => 1.0,
+        }
+    }
+
+    /// The number of samples along each axis of a pixel this pass takes with `antialiasing`.
+    ///
+    /// Supersampling only applies to the full resolution level. The adaptive pass takes this many
+    /// only on edges, but that may be every pixel.
+    pub fn samples(&self, antialiasing: Antialiasing) -> u32 {
+        match (self, antialiasing) {
+            (Pass::Level(level), antialiasing) if level.downscale == 1 => antialiasing.samples(),
+            (Pass::Adaptive, Antialiasing::Adaptive(samples)) => samples.max(1),
+            _ => 1,
+        }
+    }
+}
+
+/// The passes to render after every change of the view with the given antialiasing.
+fn schedule(antialiasing: Antialiasing) -> Vec<Pass> {
+    let mut passes: Vec<Pass> = LEVELS.into_iter().map(Pass::Level).collect();
+    match antialiasing {
+        Antialiasing::Off | Antialiasing::Supersample(_) => {}
+        Antialiasing::Jitter(frames) => {
+            passes.extend((1..frames).map(|sample| Pass::Jitter { sample }));
+        }
+        Antialiasing::Adaptive(_) => passes.push(Pass::Adaptive),
+    }
+    passes
+}
+
+/// Tracks how far the current view has been refined.
+///
+/// Every pass is split into tiles so that it can be spread over as many frames as it needs.
+/// Call [`Refinement::restart`] whenever the view changes. Then, every frame, render some of the
+/// tiles from [`Refinement::take_tiles`] for the [`Refinement::current`] pass and once
+/// [`Refinement::level_finished`] call [`Refinement::advance`]. When
+/// [`Refinement::is_converged`] is true the accumulated image is final and nothing needs to be
+/// rendered until the view changes again.
+///
+/// Refinement can be held back at a coarser level with [`Refinement::set_finest_downscale`], in
+/// which case [`Refinement::is_idle`] tells when there is nothing more to render for now.
+#[derive(Debug)]
+pub struct Refinement {
+    schedule: Vec<Pass>,
+    next: usize,
+    pending: VecDeque<Tile>,
+    /// Full resolution tiles that were still to be rendered when the view was translated, which
+    /// wait until refinement isn't held back anymore
+    deferred: VecDeque<Tile>,
+    /// Whether tiles of the current pass have been handed out yet
+    started: bool,
+    /// Passes finer than this are held back, see [`DynamicResolution`]
+    finest_downscale: u32,
+    /// Whether only the strips exposed by a [`Refinement::translate`] are being rendered
+    incremental: bool,
+    width: u32,
+    height: u32,
+}
+
+impl Refinement {
+    pub fn new(width: u32, height: u32, antialiasing: Antialiasing) -> Self {
+        let mut refinement = Self {
+            schedule: schedule(antialiasing),
+            next: 0,
+            pending: VecDeque::new(),
+            deferred: VecDeque::new(),
+            started: false,
+            finest_downscale: 1,
+            incremental: false,
+            width,
+            height,
+        };
+        refinement.restart(width, height);
+        refinement
+    }
+
+    pub fn restart(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.next = 0;
+        self.incremental = false;
+        self.queue_tiles();
+    }
+
+    /// Hold back the passes that render at a finer resolution than `1 / downscale` of the window,
+    /// including all antialiasing. A downscale of 1 lets refinement run to completion again,
+    /// picking up where it was held back.
+    ///
+    /// The strips exposed by [`Refinement::translate`] are always rendered at full resolution
+    /// since they are cheap and there is nothing coarser to show in their place.
+    pub fn set_finest_downscale(&mut self, downscale: u32) {
+        self.finest_downscale = downscale.max(1);
+    }
+
+    /// Switch to a different antialiasing mode, which starts refining from scratch.
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.schedule = schedule(antialiasing);
+        self.restart(self.width, self.height);
+    }
+
+    /// The pass to render next, or `None` if refinement is converged or held back.
+    pub fn current(&self) -> Option<Pass> {
+        let pass = self.schedule.get(self.next).copied()?;
+        let allowed = match pass {
+            Pass::Level(level) => {
+                level.downscale >= self.finest_downscale
+                    || (self.incremental && !self.pending.is_empty())
+            }
+            Pass::Adaptive | Pass::Jitter { .. } => self.finest_downscale == 1,
+        };
+        allowed.then_some(pass)
+    }
+
+    /// Whether no tiles of the current pass have been handed out yet.
+    pub fn is_pass_start(&self) -> bool {
+        !self.started
+    }
+
+    /// The tiles of the current level that [`Refinement::take_tiles`] hands out next.
+    pub fn pending_tiles(&self) -> &VecDeque<Tile> {
+        if self.pending.is_empty() && self.finest_downscale == 1 {
+            &self.deferred
+        } else {
+            &self.pending
+        }
+    }
+
+    /// Remove up to `count` tiles of the current level from the tiles still to be rendered.
+    pub fn take_tiles(&mut self, count: usize) -> Vec<Tile> {
+        self.started = true;
+        let tiles = if self.pending.is_empty() && self.finest_downscale == 1 {
+            &mut self.deferred
+        } else {
+            &mut self.pending
+        };
+        let count = count.min(tiles.len());
+        tiles.drain(..count).collect()
+    }
+
+    /// Whether all tiles of the current level have been handed out.
+    pub fn level_finished(&self) -> bool {
+        self.pending.is_empty() && self.deferred.is_empty()
+    }
+
+    pub fn advance(&mut self) {
+        self.next = (self.next + 1).min(self.schedule.len());
+        self.queue_tiles();
+    }
+
+    /// Keep refining after the view moved by a whole number of pixels, `dx` to the right and `dy`
+    /// down, and the already rendered pixels were moved along with it.
+    ///
+    /// The tiles still to be rendered move with the image and the strips that scrolled into view
+    /// are added to them. This is only possible once the full resolution level has been reached,
+    /// otherwise `false` is returned and the refinement should be restarted instead. Antialiasing
+    /// passes after the full resolution level are started over.
+    ///
+    /// Only the strips are rendered while refinement is held back, the rest of the full resolution
+    /// level is deferred until [`Refinement::set_finest_downscale`] lets it run again.
+    pub fn translate(&mut self, dx: i64, dy: i64) -> bool {
+        let full_resolution = self
+            .schedule
+            .iter()
+            .position(|pass| matches!(pass, Pass::Level(level) if level.downscale == 1))
+            .expect("the schedule reaches full resolution");
+        if self.next < full_resolution {
+            return false;
+        }
+        if self.next > full_resolution {
+            // the full resolution pass is done so only the strips that scrolled in are missing,
+            // but any antialiasing passes after it are redone for the whole view
+            self.next = full_resolution;
+            self.pending.clear();
+            self.deferred.clear();
+        }
+        if !self.incremental {
+            // what is left of the full resolution level isn't part of the strips
+            self.deferred.append(&mut self.pending);
+        }
+        self.started = true;
+        self.incremental = true;
+
+        let (width, height) = (self.width, self.height);
+        let translate = |tiles: &mut VecDeque<Tile>| -> VecDeque<Tile> {
+            tiles
+                .drain(..)
+                .filter_map(|tile| tile.translated_within(dx, dy, width, height))
+                .collect()
+        };
+        self.deferred = translate(&mut self.deferred);
+        let mut pending = translate(&mut self.pending);
+
+        // the columns that scrolled in from the left or right
+        let exposed_x = dx.clamp(-(width as i64), width as i64);
+        let columns = if exposed_x >= 0 {
+            0..exposed_x as u32
+        } else {
+            (width as i64 + exposed_x) as u32..width
+        };
+        // the rows that scrolled in from the top or bottom, leaving out the exposed columns
+        let exposed_y = dy.clamp(-(height as i64), height as i64);
+        let rows = if exposed_y >= 0 {
+            0..exposed_y as u32
+        } else {
+            (height as i64 + exposed_y) as u32..height
+        };
+        let remaining_columns = if exposed_x >= 0 {
+            columns.end..width
+        } else {
+            0..columns.start
+        };
+
+        let strips = [
+            Tile {
+                x: columns.start,
+                y: 0,
+                width: columns.len() as u32,
+                height,
+            },
+            Tile {
+                x: remaining_columns.start,
+                y: rows.start,
+                width: remaining_columns.len() as u32,
+                height: rows.len() as u32,
+            },
+        ];
+        for strip in strips {
+            if strip.area() > 0 {
+                pending.extend(tiles::split_region(strip, TILE_SIZE));
+            }
+        }
+        self.pending = pending;
+        true
+    }
+
+    fn queue_tiles(&mut self) {
+        self.pending.clear();
+        self.deferred.clear();
+        self.started = false;
+        if let Some(pass) = self.schedule.get(self.next) {
+            let (width, height) = pass.size(self.width, self.height);
+            self.pending.extend(tiles::split(width, height, TILE_SIZE));
+        }
+    }
+
+    pub fn is_converged(&self) -> bool {
+        self.next >= self.schedule.len()
+    }
+
+    /// Whether there is nothing to render until the view or the finest downscale changes.
+    pub fn is_idle(&self) -> bool {
+        self.current().is_none()
+    }
+}
+
+/// Lowers the resolution refinement goes up to while the view is being moved around.
+///
+/// Every frame of a drag or a scroll changes the view, so the refinement only ever gets to the
+/// first few passes anyway. Stopping at a coarse level keeps those frames cheap, which keeps
+/// interaction smooth on weak GPUs. Once input has been idle for `idle_interval` the full
+/// resolution and antialiasing passes are rendered.
+#[derive(Debug)]
+pub struct DynamicResolution {
+    /// How long input needs to stop before rendering at full resolution again
+    pub idle_interval: Duration,
+    /// The finest downscale that is rendered while interacting
+    pub downscale: u32,
+    /// Whether a button is held down, which counts as interacting until it is released
+    held: bool,
+    last_input: Option<Instant>,
+}
+
+impl DynamicResolution {
+    pub fn new(idle_interval: Duration, downscale: u32) -> Self {
+        Self {
+            idle_interval,
+            downscale,
+            held: false,
+            last_input: None,
+        }
+    }
+
+    /// Record input that changed the view at `now`.
+    pub fn interacted(&mut self, now: Instant) {
+        self.last_input = Some(now);
+    }
+
+    /// Record a button that drags the view being pressed or released at `now`.
+    pub fn set_held(&mut self, held: bool, now: Instant) {
+        self.held = held;
+        self.interacted(now);
+    }
+
+    pub fn is_interacting(&self, now: Instant) -> bool {
+        self.held
+            || self
+                .last_input
+                .is_some_and(|last_input| now < last_input + self.idle_interval)
+    }
+
+    /// When interaction ends, if it is still going on and not only held.
+    pub fn idle_at(&self) -> Option<Instant> {
+        if self.held {
+            return None;
+        }
+        self.last_input
+            .map(|last_input| last_input + self.idle_interval)
+    }
+
+    /// The finest downscale to render at `now`, 1 once interaction has ended.
+    pub fn finest_downscale(&mut self, now: Instant) -> u32 {
+        if self.is_interacting(now) {
+            self.downscale
+        } else {
+            self.last_input = None;
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_cover_window() {
+        let level = Level {
+            downscale: 8,
+            iteration_scale: 1.0,
+        };
+        assert_eq!(level.size(801, 600), (101, 75));
+        assert_eq!(level.size(0, 0), (1, 1));
+    }
+
+    #[test]
+    fn test_refinement_converges() {
+        let mut refinement = Refinement::new(1000, 300, Antialiasing::Off);
+        for level in LEVELS {
+            assert_eq!(refinement.current(), Some(Pass::Level(level)));
+            let (width, height) = level.size(1000, 300);
+            let tiles = refinement.take_tiles(usize::MAX);
+            assert_eq!(
+                tiles.iter().map(Tile::area).sum::<u64>(),
+                width as u64 * height as u64
+            );
+            assert!(refinement.level_finished());
+            refinement.advance();
+        }
+        assert!(refinement.is_converged());
+        assert!(refinement.take_tiles(1).is_empty());
+        refinement.restart(1000, 300);
+        assert_eq!(refinement.current(), Some(Pass::Level(LEVELS[0])));
+    }
+
+    #[test]
+    fn test_translate_renders_exposed_strips() {
+        let mut refinement = Refinement::new(1000, 300, Antialiasing::Off);
+        assert!(!refinement.translate(3, 4));
+
+        while !refinement.is_converged() {
+            refinement.take_tiles(usize::MAX);
+            refinement.advance();
+        }
+        assert!(refinement.translate(-3, 4));
+        assert_eq!(
+            refinement.current(),
+            LEVELS.last().copied().map(Pass::Level)
+        );
+        let tiles = refinement.take_tiles(usize::MAX);
+        let area = tiles.iter().map(Tile::area).sum::<u64>();
+        assert_eq!(area, 3 * 300 + 997 * 4);
+        assert!(tiles.iter().all(|tile| tile.x >= 997 || tile.y < 4));
+    }
+
+    #[test]
+    fn test_finest_downscale_holds_back_refinement() {
+        let mut refinement = Refinement::new(64, 64, Antialiasing::Adaptive(2));
+        refinement.set_finest_downscale(4);
+        let mut rendered = vec![];
+        while let Some(pass) = refinement.current() {
+            rendered.push(pass);
+            refinement.take_tiles(usize::MAX);
+            refinement.advance();
+        }
+        assert_eq!(rendered, [Pass::Level(LEVELS[0]), Pass::Level(LEVELS[1])]);
+        assert!(refinement.is_idle() && !refinement.is_converged());
+
+        refinement.set_finest_downscale(1);
+        assert_eq!(refinement.current(), Some(Pass::Level(LEVELS[2])));
+        assert_eq!(refinement.pending_tiles().len(), 1);
+    }
+
+    #[test]
+    fn test_translate_while_held_defers_full_resolution() {
+        let mut refinement = Refinement::new(512, 256, Antialiasing::Off);
+        while refinement.current() != LEVELS.last().copied().map(Pass::Level) {
+            refinement.take_tiles(usize::MAX);
+            refinement.advance();
+        }
+        // a pan starts halfway through the full resolution level
+        refinement.take_tiles(4);
+        refinement.set_finest_downscale(2);
+        assert!(refinement.is_idle());
+        assert!(refinement.translate(10, 0));
+
+        let strip = refinement.take_tiles(usize::MAX);
+        assert_eq!(strip.iter().map(Tile::area).sum::<u64>(), 10 * 256);
+        assert!(strip.iter().all(|tile| tile.x + tile.width <= 10));
+        assert!(refinement.is_idle() && !refinement.level_finished());
+
+        // the bottom row of tiles, moved along with the view and cut off at the right edge, once
+        // the pan is over
+        refinement.set_finest_downscale(1);
+        assert_eq!(
+            refinement.current(),
+            LEVELS.last().copied().map(Pass::Level)
+        );
+        let rest = refinement.take_tiles(usize::MAX);
+        assert_eq!(rest.iter().map(Tile::area).sum::<u64>(), 502 * 128);
+        assert!(rest.iter().all(|tile| tile.x >= 10 && tile.y >= 128));
+        assert!(refinement.level_finished());
+    }
+
+    #[test]
+    fn test_dynamic_resolution_recovers_after_idle() {
+        let start = Instant::now();
+        let mut dynamic_resolution = DynamicResolution::new(Duration::from_millis(100), 2);
+        assert_eq!(dynamic_resolution.finest_downscale(start), 1);
+        dynamic_resolution.set_held(true, start);
+        let later = start + Duration::from_secs(1);
+        assert_eq!(dynamic_resolution.finest_downscale(later), 2);
+        assert_eq!(dynamic_resolution.idle_at(), None);
+        dynamic_resolution.set_held(false, later);
+        assert_eq!(
+            dynamic_resolution.idle_at(),
+            Some(later + Duration::from_millis(100))
+        );
+        assert_eq!(
+            dynamic_resolution.finest_downscale(later + Duration::from_millis(100)),
+            1
+        );
+        assert_eq!(dynamic_resolution.idle_at(), None);
+    }
+
+    #[test]
+    fn test_antialiasing_passes_follow_full_resolution() {
+        let mut refinement = Refinement::new(64, 64, Antialiasing::Jitter(3));
+        while refinement.current() != Some(Pass::Jitter { sample: 1 }) {
+            refinement.take_tiles(usize::MAX);
+            refinement.advance();
+        }
+        // moving the view goes back to the full resolution pass for the exposed strip only
+        assert!(refinement.translate(0, 1));
+        assert_eq!(
+            refinement.current(),
+            LEVELS.last().copied().map(Pass::Level)
+        );
+        let strip = refinement.take_tiles(usize::MAX);
+        assert_eq!(strip.iter().map(Tile::area).sum::<u64>(), 64);
+        refinement.advance();
+        assert_eq!(refinement.current(), Some(Pass::Jitter { sample: 1 }));
+        refinement.advance();
+        assert_eq!(refinement.current(), Some(Pass::Jitter { sample: 2 }));
+        refinement.advance();
+        assert!(refinement.is_converged());
+    }
+}
@@ -0,0 +1,139 @@
+//! Turning the view by dragging the mouse.
+
+use std::f64::consts::TAU;
+
+use kurbo::{Affine, Vec2};
+use serde::Deserialize;
+
+/// Radians the view turns by for every pixel the mouse is dragged sideways about the cursor
+const ROTATION_PER_PIXEL: f64 = TAU / 720.0;
+
+/// What a rotation drag turns the view about.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pivot {
+    /// The center of the window, following the angle of the cursor around it like a dial
+    #[default]
+    Center,
+    /// Where the drag started, turning clockwise as the mouse moves right
+    Cursor,
+}
+
+/// A rotation of the view being dragged, measured from where it started so that snapping and
+/// rounding errors don't build up over the drag.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RotationDrag {
+    pivot: Pivot,
+    /// The point the view turns about, in window pixels
+    center: Vec2,
+    /// Where the cursor was when the drag started
+    start: Vec2,
+    /// The window transform when the drag started
+    transform: Affine,
+    /// The rotation of the view when the drag started, see [`crate::view::View::rotation`]
+    rotation: f64,
+}
+
+impl RotationDrag {
+    /// Start turning a window of size `viewport` showing `transform`, whose view has `rotation`,
+    /// with the cursor at `start`.
+    pub fn new(
+        pivot: Pivot,
+        start: Vec2,
+        viewport: Vec2,
+        transform: Affine,
+        rotation: f64,
+    ) -> Self {
+        let center = match pivot {
+            Pivot::Center => viewport / 2.0,
+            Pivot::Cursor => start,
+        };
+        Self {
+            pivot,
+            center,
+            start,
+            transform,
+            rotation,
+        }
+    }
+
+    /// The window transform with the cursor at `position`.
+    ///
+    /// With `snap` the view's rotation only takes multiples of that many radians, so a view that
+    /// started out straight stays straight at right angles and the like.
+    pub fn update(&self, position: Vec2, snap: Option<f64>) -> Affine {
+        let mut angle = match self.pivot {
+            Pivot::Center => (position - self.center).atan2() - (self.start - self.center).atan2(),
+            Pivot::Cursor => (position.x - self.start.x) * ROTATION_PER_PIXEL,
+        };
+        if let Some(snap) = snap.filter(|&snap| snap > 0.0) {
+            angle = snap_angle(self.rotation + angle, snap) - self.rotation;
+        }
+        // turning the window transform by an angle turns the view by the same angle
+        Affine::translate(self.center)
+            * Affine::rotate(angle)
+            * Affine::translate(-self.center)
+            * self.transform
+    }
+}
+
+/// `angle` rounded to the nearest multiple of `snap`.
+pub fn snap_angle(angle: f64, snap: f64) -> f64 {
+    (angle / snap).round() * snap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::View;
+
+    const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+    #[test]
+    fn test_dial_follows_cursor() {
+        let view = View::default();
+        let transform = view.to_transform(VIEWPORT);
+        let start = Vec2::new(600.0, 300.0);
+        let drag = RotationDrag::new(Pivot::Center, start, VIEWPORT, transform, view.rotation);
+        // a quarter turn of the cursor around the center
+        let turned = View::from_transform(drag.update(Vec2::new(400.0, 500.0), None), VIEWPORT);
+        assert!((turned.rotation - TAU / 4.0).abs() < 1e-9);
+        assert!((turned.center - view.center).hypot() < 1e-9);
+        assert!((turned.zoom - view.zoom).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pivot_at_cursor_stays_in_place() {
+        let view = View::new(Vec2::new(-0.75, 0.1), 20.0);
+        let transform = view.to_transform(VIEWPORT);
+        let start = Vec2::new(100.0, 150.0);
+        let drag = RotationDrag::new(Pivot::Cursor, start, VIEWPORT, transform, view.rotation);
+        let turned = View::from_transform(drag.update(Vec2::new(280.0, 400.0), None), VIEWPORT);
+        assert!((turned.rotation - 180.0 * ROTATION_PER_PIXEL).abs() < 1e-9);
+        let pivot = view.complex_at(start, VIEWPORT);
+        assert!((turned.complex_at(start, VIEWPORT) - pivot).hypot() < 1e-12);
+    }
+
+    #[test]
+    fn test_snapping() {
+        let snap = 15f64.to_radians();
+        assert!((snap_angle(0.3, snap) - snap).abs() < 1e-12);
+        assert_eq!(snap_angle(-0.1, snap), 0.0);
+
+        let view = View {
+            rotation: 0.05,
+            ..View::default()
+        };
+        let transform = view.to_transform(VIEWPORT);
+        let start = Vec2::new(400.0, 300.0);
+        let drag = RotationDrag::new(Pivot::Cursor, start, VIEWPORT, transform, view.rotation);
+        let position = start + Vec2::new(40.0, 0.0);
+        let snapped = View::from_transform(drag.update(position, Some(snap)), VIEWPORT);
+        assert!((snapped.rotation - 2.0 * snap).abs() < 1e-9);
+        // no snapping without an angle to snap to
+        assert_eq!(
+            drag.update(position, Some(0.0)),
+            drag.update(position, None)
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Rubber-band rectangles dragged out over the window to zoom to.
+
+use kurbo::{Affine, Vec2};
+
+use crate::transforms::fit_rectangle;
+
+/// Rectangles smaller than this many pixels on a side are taken for a click and ignored
+const MIN_SIZE: f64 = 4.0;
+
+/// A rectangle being dragged out between two corners, in window pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Selection {
+    /// Where the drag started
+    pub start: Vec2,
+    /// Where the cursor is now
+    pub end: Vec2,
+    /// Whether releasing zooms out so the current view fits into the rectangle instead of in so
+    /// that the rectangle fills the window
+    pub zoom_out: bool,
+}
+
+impl Selection {
+    pub fn new(start: Vec2, zoom_out: bool) -> Self {
+        Self {
+            start,
+            end: start,
+            zoom_out,
+        }
+    }
+
+    /// The top left and bottom right corners of the rectangle.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let min = Vec2::new(self.start.x.min(self.end.x), self.start.y.min(self.end.y));
+        let max = Vec2::new(self.start.x.max(self.end.x), self.start.y.max(self.end.y));
+        (min, max)
+    }
+
+    /// Whether the rectangle is too small to zoom to.
+    pub fn is_empty(&self) -> bool {
+        let (min, max) = self.bounds();
+        max.x - min.x < MIN_SIZE || max.y - min.y < MIN_SIZE
+    }
+
+    /// The change to the window transform that zooms to the rectangle in a window of size
+    /// `viewport`, or `None` if it is empty.
+    ///
+    /// Zooming in shows all of the rectangle as large as it fits, zooming out shows all of the
+    /// window as large as it fits into the rectangle. Either way the aspect ratio is kept, so
+    /// more than the rectangle covers ends up in view along one axis.
+    pub fn zoom(&self, viewport: Vec2) -> Option<Affine> {
+        if self.is_empty() {
+            return None;
+        }
+        let (min, max) = self.bounds();
+        Some(if self.zoom_out {
+            fit_rectangle(Vec2::ZERO, viewport, min, max)
+        } else {
+            fit_rectangle(min, max, Vec2::ZERO, viewport)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_in_fills_window() {
+        let viewport = Vec2::new(800.0, 600.0);
+        let mut selection = Selection::new(Vec2::new(300.0, 250.0), false);
+        selection.end = Vec2::new(100.0, 200.0);
+        assert_eq!(
+            selection.bounds(),
+            (Vec2::new(100.0, 200.0), Vec2::new(300.0, 250.0))
+        );
+        let zoom = selection.zoom(viewport).unwrap();
+        // a wide selection fills the width of the window around its center
+        let center = zoom * kurbo::Point::new(200.0, 225.0);
+        assert!((center.to_vec2() - viewport / 2.0).hypot() < 1e-9);
+        assert!((zoom.determinant().sqrt() - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_zoom_out_undoes_zoom_in() {
+        let viewport = Vec2::new(800.0, 600.0);
+        let mut selection = Selection::new(Vec2::new(100.0, 100.0), false);
+        selection.end = Vec2::new(300.0, 250.0);
+        let zoom_in = selection.zoom(viewport).unwrap();
+        selection.zoom_out = true;
+        let zoom_out = selection.zoom(viewport).unwrap();
+        // for a rectangle with the window's aspect ratio
+        for (a, b) in (zoom_in * zoom_out)
+            .as_coeffs()
+            .iter()
+            .zip([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+        {
+            assert!((a - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_clicks_are_empty() {
+        let mut selection = Selection::new(Vec2::new(100.0, 100.0), false);
+        selection.end = Vec2::new(102.0, 300.0);
+        assert!(selection.is_empty());
+        assert_eq!(selection.zoom(Vec2::new(800.0, 600.0)), None);
+    }
+}
@@ -0,0 +1,12 @@
+use crate::{antialiasing::Antialiasing, palette::Palette};
+
+/// How the set is rendered, as opposed to which part of it is shown, which is a
+/// [`View`](crate::view::View).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Settings {
+    pub palette: Palette,
+    /// Iteration limit for every pixel, `None` to raise it with the zoom level
+    pub max_iterations: Option<u32>,
+    /// In the window every mode applies, still images only use supersampling
+    pub antialiasing: Antialiasing,
+}
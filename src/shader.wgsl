@@ -24,11 +24,37 @@ fn transform_point(affine: Affine, point: vec2<f32>) -> vec2<f32> {
 struct Globals {
     transform: Affine,
     viewport: vec2<f32>,
+    // fraction of max_i that the current refinement pass may spend
+    iteration_scale: f32,
+    // number of samples along each axis of a pixel
+    samples: u32,
+    // offset of the samples from where they would be without jitter, in pixels
+    jitter: vec2<f32>,
+    // difference in normalized iteration count between neighbors that adaptive antialiasing resamples
+    edge_threshold: f32,
+    // when non zero alpha holds the normalized iteration count instead of 1
+    iteration_alpha: u32,
+    // iteration limit, 0 to raise it with the zoom level
+    max_iterations: u32,
+    // every channel is offset + amplitude * cos(frequency * t + phase)
+    palette_frequency: f32,
+    // center of the log-polar mapping
+    exp_map_center: vec2<f32>,
+    palette_offset: vec4<f32>,
+    palette_amplitude: vec4<f32>,
+    palette_phase: vec4<f32>,
+    // log of the radius and angle at the top left pixel and their change per pixel in z, when
+    // that isn't 0 pixels are mapped log-polar around exp_map_center instead of through transform
+    exp_map: vec4<f32>,
 };
 
 @group(0) @binding(0)
 var<uniform> globals: Globals;
 
+// the accumulated image before the adaptive antialiasing pass
+@group(1) @binding(0)
+var snapshot: texture_2d<f32>;
+
 struct VertexInput {
     @location(0) pos: vec2<f32>,
     @builtin(vertex_index) index: u32,
@@ -37,7 +63,8 @@ struct VertexInput {
 
 struct VertexOutput {
     @builtin(position) pos: vec4<f32>,
-    @location(0) uv: vec2<f32>,
+    // position in window pixels, the transform maps it onto the complex plane
+    @location(0) pixel: vec2<f32>,
     @location(1) zoom_factor: f32,
 };
 
@@ -51,28 +78,26 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     // it is important that this coordinate space (where the origin is and which way is growing) matches winit
     switch (in.index) {
         case 0u,3u: {
-            out.uv = vec2<f32>(0.0, vy); // Bottom-left
+            out.pixel = vec2<f32>(0.0, vy); // Bottom-left
             break;
         }
         case 1u: {
-            out.uv = vec2<f32>(vx, vy); // Bottom-right
+            out.pixel = vec2<f32>(vx, vy); // Bottom-right
             break;
         }
         case 2u,4u: {
-            out.uv = vec2<f32>(vx, 0.0); // Top-right
+            out.pixel = vec2<f32>(vx, 0.0); // Top-right
             break;
         }
         case 5u: {
-            out.uv = vec2<f32>(0.0, 0.0); // Top-left
+            out.pixel = vec2<f32>(0.0, 0.0); // Top-left
             break;
         }
         default: {
-            out.uv = vec2<f32>(0.0, 0.0); // Default case
+            out.pixel = vec2<f32>(0.0, 0.0); // Default case
         }
     }
 
-    out.uv = transform_point(globals.transform, out.uv);
-
     let a = globals.transform.elements[0].x;
     let b = globals.transform.elements[0].y;
     let d = globals.transform.elements[0].w;
@@ -82,17 +107,31 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     return out;
 }
 
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let c = in.uv;
+// The point of the complex plane shown at `pixel`
+fn pixel_to_complex(pixel: vec2<f32>) -> vec2<f32> {
+    let step = globals.exp_map.z;
+    if (step == 0.0) {
+        return transform_point(globals.transform, pixel);
+    }
+    let radius = exp(globals.exp_map.x - pixel.y * step);
+    let angle = globals.exp_map.y + pixel.x * step;
+    return globals.exp_map_center + radius * vec2<f32>(cos(angle), sin(angle));
+}
 
+// Normalized iteration count of the point `c`, 1 for points that are considered inside
+fn escape_time(c: vec2<f32>, max_i: u32) -> f32 {
     var z = vec2<f32>(0.0, 0.0);
     var i = 0u;
-    let max_i = u32(100.0 * log2(in.zoom_factor));
+    let budget = max(u32(f32(max_i) * globals.iteration_scale), 1u);
     let epsilon = 1e-3 ; // Threshold for change in z
 
     loop {
-        if (i >= max_i) { break; }
+        // points that exhaust a reduced budget are treated as inside so that coarse passes are
+        // colored the same way as the final one
+        if (i >= budget) {
+            i = max_i;
+            break;
+        }
         if (dot(z, z) > 4.0) { break; }
 
         let z_new = vec2<f32>(
@@ -110,12 +149,59 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         i += 1u;
     }
 
-    let t = f32(i) / f32(max_i);
-    let color = vec4<f32>(
-        0.5 + 0.5 * cos(3.0 + 6.28318 * t),
-        0.5 + 0.5 * cos(3.0 + 6.28318 * t + 2.0),
-        0.5 + 0.5 * cos(3.0 + 6.28318 * t + 4.0),
-        1.0
+    return f32(i) / f32(max_i);
+}
+
+fn palette(t: f32) -> vec3<f32> {
+    return globals.palette_offset.xyz
+        + globals.palette_amplitude.xyz * cos(globals.palette_frequency * t + globals.palette_phase.xyz);
+}
+
+// Average color of a grid of `samples` by `samples` points spread over the pixel at `pixel`
+fn shade(pixel: vec2<f32>, zoom_factor: f32, samples: u32) -> vec4<f32> {
+    var max_i = globals.max_iterations;
+    if (max_i == 0u) {
+        max_i = u32(100.0 * log2(zoom_factor));
+    }
+    var color = vec3<f32>(0.0);
+    var t_sum = 0.0;
+    for (var y = 0u; y < samples; y++) {
+        for (var x = 0u; x < samples; x++) {
+            let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / f32(samples) - 0.5 + globals.jitter;
+            let t = escape_time(pixel_to_complex(pixel + offset), max_i);
+            color += palette(t);
+            t_sum += t;
+        }
+    }
+    let count = f32(samples * samples);
+    var alpha = 1.0;
+    if (globals.iteration_alpha != 0u) {
+        alpha = t_sum / count;
+    }
+    return vec4<f32>(color / count, alpha);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in.pixel, in.zoom_factor, globals.samples);
+}
+
+// Resample pixels on edges between different iteration counts and leave all others alone
+@fragment
+fn fs_adaptive(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(snapshot));
+    let center = vec2<i32>(in.pos.xy);
+    let t = textureLoad(snapshot, center, 0).a;
+    var difference = 0.0;
+    var neighbors = array<vec2<i32>, 4>(
+        vec2<i32>(-1, 0), vec2<i32>(1, 0), vec2<i32>(0, -1), vec2<i32>(0, 1)
     );
-    return color;
+    for (var n = 0; n < 4; n++) {
+        let neighbor = clamp(center + neighbors[n], vec2<i32>(0), size - 1);
+        difference = max(difference, abs(textureLoad(snapshot, neighbor, 0).a - t));
+    }
+    if (difference < globals.edge_threshold) {
+        discard;
+    }
+    return shade(in.pixel, in.zoom_factor, globals.samples);
 }
@@ -0,0 +1,69 @@
+// Size of a character cell and where its glyph starts within it, in font pixels
+const CELL = vec2<f32>(6.0, 11.0);
+const GLYPH_OFFSET = vec2<i32>(0, 2);
+const GLYPH_SIZE = vec2<i32>(5, 7);
+
+struct Text {
+    color: vec4<f32>,
+    background: vec4<f32>,
+    // top left corner of the first cell in pixels
+    origin: vec2<f32>,
+    viewport: vec2<f32>,
+    // pixels per font pixel
+    scale: f32,
+    // two glyphs per vector, see `font::packed`
+    font: array<vec4<u32>, 48>,
+};
+
+@group(0) @binding(0)
+var<uniform> text: Text;
+
+struct CellOutput {
+    @builtin(position) position: vec4<f32>,
+    // position within the cell in font pixels
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) glyph: u32,
+};
+
+// Two triangles covering the character cell of each instance
+@vertex
+fn vs_text(
+    @builtin(vertex_index) index: u32,
+    @location(0) cell: vec2<u32>,
+    @location(1) glyph: u32,
+) -> CellOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let local = corners[index] * CELL;
+    let pixel = text.origin + (vec2<f32>(cell) * CELL + local) * text.scale;
+    var out: CellOutput;
+    out.position = vec4<f32>(
+        pixel.x / text.viewport.x * 2.0 - 1.0,
+        1.0 - pixel.y / text.viewport.y * 2.0,
+        0.0,
+        1.0,
+    );
+    out.local = local;
+    out.glyph = glyph;
+    return out;
+}
+
+@fragment
+fn fs_text(in: CellOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(floor(in.local)) - GLYPH_OFFSET;
+    if (all(pixel >= vec2<i32>(0)) && all(pixel < GLYPH_SIZE)) {
+        let bit = u32(pixel.y * GLYPH_SIZE.x + pixel.x);
+        let pair = text.font[in.glyph / 2u];
+        let word = pair[(in.glyph % 2u) * 2u + bit / 32u];
+        if (((word >> (bit % 32u)) & 1u) == 1u) {
+            return text.color;
+        }
+    }
+    return text.background;
+}
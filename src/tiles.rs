@@ -0,0 +1,108 @@
+/// A rectangle of pixels, in the coordinate space of the texture it is rendered into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Move the tile by `dx`, `dy` pixels and cut off whatever ends up outside of a `width` by
+    /// `height` image. Returns `None` if nothing of the tile is left.
+    pub fn translated_within(&self, dx: i64, dy: i64, width: u32, height: u32) -> Option<Tile> {
+        let x0 = (self.x as i64 + dx).clamp(0, width as i64);
+        let y0 = (self.y as i64 + dy).clamp(0, height as i64);
+        let x1 = (self.x as i64 + self.width as i64 + dx).clamp(0, width as i64);
+        let y1 = (self.y as i64 + self.height as i64 + dy).clamp(0, height as i64);
+        (x1 > x0 && y1 > y0).then(|| Tile {
+            x: x0 as u32,
+            y: y0 as u32,
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+        })
+    }
+}
+
+/// Split a `width` by `height` image into tiles of at most `tile_size` by `tile_size` pixels.
+///
+/// Tiles are returned row by row starting from the top left. Tiles along the right and bottom
+/// edges are cut short so that no tile reaches outside of the image.
+pub fn split(width: u32, height: u32, tile_size: u32) -> Vec<Tile> {
+    split_region(
+        Tile {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        },
+        tile_size,
+    )
+}
+
+/// Like [`split`] but for a part of an image.
+pub fn split_region(region: Tile, tile_size: u32) -> Vec<Tile> {
+    assert!(tile_size > 0, "tiles need to be at least one pixel wide");
+    let Tile {
+        x: left,
+        y: top,
+        width,
+        height,
+    } = region;
+    (0..height)
+        .step_by(tile_size as usize)
+        .flat_map(|y| {
+            (0..width).step_by(tile_size as usize).map(move |x| Tile {
+                x: left + x,
+                y: top + y,
+                width: tile_size.min(width - x),
+                height: tile_size.min(height - y),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_covers_image() {
+        let tiles = split(300, 130, 128);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[2],
+            Tile {
+                x: 256,
+                y: 0,
+                width: 44,
+                height: 128
+            }
+        );
+        assert_eq!(tiles.iter().map(Tile::area).sum::<u64>(), 300 * 130);
+    }
+
+    #[test]
+    fn test_translated_within() {
+        let tile = Tile {
+            x: 10,
+            y: 10,
+            width: 20,
+            height: 20,
+        };
+        assert_eq!(
+            tile.translated_within(-15, 5, 100, 25),
+            Some(Tile {
+                x: 0,
+                y: 15,
+                width: 15,
+                height: 10
+            })
+        );
+        assert_eq!(tile.translated_within(100, 0, 100, 100), None);
+    }
+}
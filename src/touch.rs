@@ -0,0 +1,164 @@
+//! Panning, zooming and rotating with fingers on a touchscreen.
+
+use std::collections::BTreeMap;
+
+use kurbo::{Affine, Vec2};
+
+/// Fingers closer together than this many pixels, in root mean square distance from their
+/// centroid, can only pan since their spread is too small to tell zoom and rotation from
+const MIN_SPREAD: f64 = 1.0;
+
+/// The fingers on the window, by the id the platform gives them, in window pixels.
+#[derive(Clone, Debug, Default)]
+pub struct Touches {
+    fingers: BTreeMap<u64, Vec2>,
+}
+
+impl Touches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many fingers are down.
+    pub fn len(&self) -> usize {
+        self.fingers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fingers.is_empty()
+    }
+
+    /// Finger `id` touched the window at `position`.
+    pub fn start(&mut self, id: u64, position: Vec2) {
+        self.fingers.insert(id, position);
+    }
+
+    /// Finger `id` was lifted or the touch was cancelled.
+    pub fn end(&mut self, id: u64) {
+        self.fingers.remove(&id);
+    }
+
+    /// Move finger `id` to `position` and return the change to the window transform that keeps
+    /// the image under the fingers, or `None` if the finger isn't down.
+    ///
+    /// One finger pans. With more, the image is zoomed and rotated about the centroid of the
+    /// fingers as it moves with it, by the similarity transform that fits the fingers' movement
+    /// best, which for two fingers keeps both exactly on the same points.
+    pub fn moved(&mut self, id: u64, position: Vec2) -> Option<Affine> {
+        let before = self.fingers.clone();
+        *self.fingers.get_mut(&id)? = position;
+        Some(similarity(&before, &self.fingers))
+    }
+
+    /// The centroid of the fingers, if there are any.
+    pub fn centroid(&self) -> Option<Vec2> {
+        centroid(&self.fingers)
+    }
+}
+
+fn centroid(fingers: &BTreeMap<u64, Vec2>) -> Option<Vec2> {
+    let sum = fingers.values().fold(Vec2::ZERO, |sum, &it| sum + it);
+    (!fingers.is_empty()).then(|| sum / fingers.len() as f64)
+}
+
+/// The least squares similarity transform from the fingers in `before` to those in `after`.
+///
+/// Taking the points as complex numbers relative to their centroids, the best fit scaled rotation
+/// is `Σ conj(p) q / Σ |p|²`.
+fn similarity(before: &BTreeMap<u64, Vec2>, after: &BTreeMap<u64, Vec2>) -> Affine {
+    let (Some(from), Some(to)) = (centroid(before), centroid(after)) else {
+        return Affine::IDENTITY;
+    };
+    let (mut dot, mut cross, mut norm, mut after_norm) = (0.0, 0.0, 0.0, 0.0);
+    for (p, q) in before.values().zip(after.values()) {
+        let (p, q) = (*p - from, *q - to);
+        dot += p.dot(q);
+        cross += p.cross(q);
+        norm += p.hypot2();
+        after_norm += q.hypot2();
+    }
+    // a single finger, or fingers on top of each other before or after the move, can only pan,
+    // which also keeps pinching them together from collapsing the view
+    let min_norm = MIN_SPREAD.powi(2) * before.len() as f64;
+    let (re, im) = if norm >= min_norm && after_norm >= min_norm {
+        (dot / norm, cross / norm)
+    } else {
+        (1.0, 0.0)
+    };
+    Affine::translate(to) * Affine::new([re, im, -im, re, 0.0, 0.0]) * Affine::translate(-from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_maps(transform: Affine, from: Vec2, to: Vec2) {
+        let mapped = (transform * from.to_point()).to_vec2();
+        assert!(
+            (mapped - to).hypot() < 1e-9,
+            "{from:?} went to {mapped:?}, not {to:?}"
+        );
+    }
+
+    #[test]
+    fn test_one_finger_pans() {
+        let mut touches = Touches::new();
+        touches.start(3, Vec2::new(100.0, 100.0));
+        let change = touches.moved(3, Vec2::new(130.0, 90.0)).unwrap();
+        assert_eq!(change, Affine::translate(Vec2::new(30.0, -10.0)));
+        assert_eq!(touches.moved(4, Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn test_pinch_keeps_fingers_in_place() {
+        let mut touches = Touches::new();
+        let (a, b) = (Vec2::new(100.0, 100.0), Vec2::new(200.0, 100.0));
+        touches.start(0, a);
+        touches.start(1, b);
+
+        // spreading the fingers zooms in about their centroid
+        let spread = touches.moved(1, Vec2::new(300.0, 100.0)).unwrap();
+        assert_maps(spread, a, a);
+        assert_maps(spread, b, Vec2::new(300.0, 100.0));
+        assert!((spread.determinant().sqrt() - 2.0).abs() < 1e-9);
+
+        // turning them rotates
+        let turn = touches.moved(0, Vec2::new(200.0, 0.0)).unwrap();
+        assert_maps(turn, a, Vec2::new(200.0, 0.0));
+        assert_maps(turn, Vec2::new(300.0, 100.0), Vec2::new(300.0, 100.0));
+        let [re, im, ..] = turn.as_coeffs();
+        assert!((im.atan2(re) - std::f64::consts::FRAC_PI_4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pinching_onto_one_point_only_pans() {
+        let mut touches = Touches::new();
+        touches.start(0, Vec2::new(100.0, 100.0));
+        touches.start(1, Vec2::new(200.0, 100.0));
+        let change = touches.moved(1, Vec2::new(100.0, 100.0)).unwrap();
+        assert_eq!(change, Affine::translate(Vec2::new(-50.0, 0.0)));
+        // and spreading them again from there
+        let change = touches.moved(0, Vec2::new(0.0, 100.0)).unwrap();
+        assert_eq!(change, Affine::translate(Vec2::new(-50.0, 0.0)));
+    }
+
+    #[test]
+    fn test_fingers_coming_and_going() {
+        let mut touches = Touches::new();
+        touches.start(0, Vec2::new(100.0, 100.0));
+        touches.start(1, Vec2::new(200.0, 200.0));
+        touches.start(2, Vec2::new(300.0, 0.0));
+        assert_eq!(touches.centroid(), Some(Vec2::new(200.0, 100.0)));
+        // with more than two fingers the fit still carries the centroid along
+        let change = touches.moved(2, Vec2::new(330.0, 0.0)).unwrap();
+        assert_maps(change, Vec2::new(200.0, 100.0), Vec2::new(210.0, 100.0));
+        touches.end(1);
+        touches.end(2);
+        assert_eq!(touches.len(), 1);
+        // lifting fingers doesn't move anything until the rest move
+        assert_eq!(
+            touches.moved(0, Vec2::new(100.0, 100.0)),
+            Some(Affine::IDENTITY)
+        );
+    }
+}
@@ -53,6 +53,8 @@ use kurbo::{Affine, Vec2};
 /// # Example Usage
 ///
 /// ```rust
+/// # use kurbo::{Affine, Vec2};
+/// # use wgpu_mandelbrot::transforms::transform_point;
 /// let affine = Affine::new([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
 /// let point = Vec2::new(1.0, 1.0);
 /// let transformed_point = transform_point(affine, point);
@@ -91,7 +93,7 @@ pub fn transform_point(affine: Affine, point: Vec2) -> Vec2 {
 /// This function calculates the necessary scaling factors to adjust the aspect ratio of a 2D object.
 /// The aspect ratio is defined as the ratio of width to height. For example:
 ///
-/// ```
+/// ```text
 /// original_aspect_ratio = 4.0 / 3.0  // (width / height)
 /// new_aspect_ratio = 16.0 / 9.0      // (width / height)
 /// ```
@@ -100,7 +102,7 @@ pub fn transform_point(affine: Affine, point: Vec2) -> Vec2 {
 /// it differently along the x and y axes. This is achieved by calculating the `x_scale` and `y_scale`
 /// factors:
 ///
-/// ```
+/// ```text
 /// x_scale = (new_aspect_ratio / original_aspect_ratio).min(1.0)
 /// y_scale = (original_aspect_ratio / new_aspect_ratio).min(1.0)
 /// ```
@@ -112,7 +114,7 @@ pub fn transform_point(affine: Affine, point: Vec2) -> Vec2 {
 /// Consider an object with an original aspect ratio of 4:3 that needs to be transformed to an aspect ratio of 16:9.
 ///
 /// Original Aspect Ratio (4:3):
-/// ```
+/// ```text
 /// +---------+
 /// |         |
 /// |         |
@@ -121,7 +123,7 @@ pub fn transform_point(affine: Affine, point: Vec2) -> Vec2 {
 /// ```
 ///
 /// New Aspect Ratio (16:9):
-/// ```
+/// ```text
 /// +-----------------+
 /// |                 |
 /// |                 |
@@ -139,7 +141,7 @@ pub fn transform_point(affine: Affine, point: Vec2) -> Vec2 {
 ///
 /// # Example
 ///
-/// ```
+/// ```text
 /// let original_aspect_ratio = 4.0 / 3.0;
 /// let new_aspect_ratio = 16.0 / 9.0;
 /// let affine = aspect_ratio(original_aspect_ratio, new_aspect_ratio);
@@ -199,6 +201,121 @@ pub fn general_transform(
     translate_transform * scale_transform
 }
 
+/// Create an affine transform that fits one rectangle into another without distorting it.
+///
+/// # Arguments
+///
+/// * `from_min` - The minimum point (Vec2) of the rectangle to move.
+/// * `from_max` - The maximum point (Vec2) of the rectangle to move.
+/// * `to_min` - The minimum point (Vec2) of the rectangle to fit it into.
+/// * `to_max` - The maximum point (Vec2) of the rectangle to fit it into.
+///
+/// # Returns
+///
+/// * `Affine` - The transform that scales the first rectangle uniformly to the largest size that
+///   fits into the second one and centers it there. Unlike [`general_transform`], which stretches
+///   it to fill the second rectangle exactly, the aspect ratio is kept with
+///   [`aspect_ratio_correction`].
+pub fn fit_rectangle(from_min: Vec2, from_max: Vec2, to_min: Vec2, to_max: Vec2) -> Affine {
+    let stretch = general_transform(from_min, from_max, to_min, to_max);
+    let aspect_ratio = |min: Vec2, max: Vec2| (max.x - min.x) / (max.y - min.y);
+    let correction = aspect_ratio_correction(
+        aspect_ratio(to_min, to_max),
+        aspect_ratio(from_min, from_max),
+    );
+    let center = (to_min + to_max) / 2.0;
+    Affine::translate(center) * correction * Affine::translate(-center) * stretch
+}
+
+/// Create the affine transform from window pixels to points of the complex plane.
+///
+/// # Arguments
+///
+/// * `viewport` - The size of the window in pixels.
+/// * `transform` - How the user moved the image around in the window. The identity shows the whole
+///   Mandelbrot set, from -2 to 1 on the real axis and -1 to 1 on the imaginary axis, as large as
+///   it fits in the window.
+///
+/// # Returns
+///
+/// * `Affine` - The transform that takes the pixel coordinates of the window, with the origin in
+///   the top left corner, to the point of the complex plane shown at that pixel.
+pub fn pixel_to_complex(viewport: Vec2, transform: Affine) -> Affine {
+    let mandelbrot_min = Vec2::new(-2.0, -1.0);
+    let mandelbrot_max = Vec2::new(1.0, 1.0);
+    let viewport_to_mandelbrot =
+        general_transform(Vec2::new(0., 0.), viewport, mandelbrot_min, mandelbrot_max);
+
+    // Calculate the aspect ratio of the Mandelbrot space
+    let mandelbrot_aspect_ratio =
+        (mandelbrot_max.x - mandelbrot_min.x) / (mandelbrot_max.y - mandelbrot_min.y);
+
+    // Compute the aspect ratio correction
+    let aspect_ratio_correction = aspect_ratio_correction(
+        viewport.x / viewport.y, // Aspect ratio of the viewport
+        mandelbrot_aspect_ratio, // Aspect ratio of the Mandelbrot space
+    )
+    .inverse();
+
+    aspect_ratio_correction * viewport_to_mandelbrot * transform.inverse()
+}
+
+/// Check whether an affine transform only moves points by a whole number of pixels.
+///
+/// # Arguments
+///
+/// * `affine` - The transform to check, in pixel coordinates.
+///
+/// # Returns
+///
+/// * `Option<(i64, i64)>` - The horizontal and vertical offset in pixels if the transform is a pure
+///   translation by whole pixels (up to floating point noise), `None` otherwise.
+pub fn integer_translation(affine: Affine) -> Option<(i64, i64)> {
+    const EPSILON: f64 = 1e-6;
+    let [a, b, c, d, e, f] = affine.as_coeffs();
+    let is_translation = (a - 1.0).abs() < EPSILON
+        && b.abs() < EPSILON
+        && c.abs() < EPSILON
+        && (d - 1.0).abs() < EPSILON;
+    let is_whole = (e - e.round()).abs() < EPSILON && (f - f.round()).abs() < EPSILON;
+    (is_translation && is_whole).then(|| (e.round() as i64, f.round() as i64))
+}
+
+/// Map a pixel of an exponential map onto the complex plane.
+///
+/// An exponential map is a log-polar image: columns go around `center` once and rows go inward,
+/// each row at a radius smaller than the one above by the same factor. With `step` as the change
+/// in both the angle and the log of the radius per pixel, features keep their shape no matter how
+/// deep they are, so one image holds many decades of zoom at the resolution of a single frame.
+///
+/// # Arguments
+///
+/// * `center` - The point the map zooms in on.
+/// * `log_radius` - The natural log of the radius at the top edge of the map.
+/// * `step` - The change in angle and log radius from one pixel to the next, `TAU / width` for a
+///   map that goes around once.
+/// * `pixel` - The position in the map, with the origin in the top left corner.
+///
+/// # Returns
+///
+/// * `Vec2` - The point of the complex plane at that position.
+pub fn exp_map_to_complex(center: Vec2, log_radius: f64, step: f64, pixel: Vec2) -> Vec2 {
+    let radius = (log_radius - pixel.y * step).exp();
+    let angle = pixel.x * step;
+    center + Vec2::from_angle(angle) * radius
+}
+
+/// The inverse of [`exp_map_to_complex`], for points other than `center` itself.
+///
+/// # Returns
+///
+/// * `Vec2` - The position in the map, with an x between 0 and the width of a full turn.
+pub fn complex_to_exp_map(center: Vec2, log_radius: f64, step: f64, point: Vec2) -> Vec2 {
+    let offset = point - center;
+    let angle = offset.atan2().rem_euclid(std::f64::consts::TAU);
+    Vec2::new(angle / step, (log_radius - offset.hypot().ln()) / step)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -239,4 +356,69 @@ mod tests {
         let expected_point = Vec2::new(1.0, 0.5);
         assert_eq!(transformed_point, expected_point);
     }
+
+    #[test]
+    fn test_exp_map_round_trip() {
+        let center = Vec2::new(-0.75, 0.1);
+        let step = std::f64::consts::TAU / 360.0;
+        for pixel in [
+            Vec2::new(0.5, 0.5),
+            Vec2::new(359.0, 10.0),
+            Vec2::new(90.0, 500.0),
+        ] {
+            let point = exp_map_to_complex(center, 0.0, step, pixel);
+            _assert_near(complex_to_exp_map(center, 0.0, step, point), pixel);
+        }
+        // a full turn down the map shrinks the radius by e^TAU
+        let top = exp_map_to_complex(center, 0.0, step, Vec2::new(0.0, 0.0));
+        let below = exp_map_to_complex(center, 0.0, step, Vec2::new(0.0, 360.0));
+        let ratio = (top - center).hypot() / (below - center).hypot();
+        assert!((ratio.ln() - std::f64::consts::TAU).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_integer_translation() {
+        let drag =
+            Affine::translate(Vec2::new(-3.0, 12.0)) * Affine::translate(Vec2::new(1.0, 0.0));
+        assert_eq!(integer_translation(drag), Some((-2, 12)));
+        assert_eq!(
+            integer_translation(Affine::translate(Vec2::new(0.5, 0.0))),
+            None
+        );
+        assert_eq!(integer_translation(Affine::scale(1.05)), None);
+    }
+
+    #[test]
+    fn test_fit_rectangle() {
+        // a square into a wide rectangle is as tall as the rectangle and centered in it
+        let fit = fit_rectangle(
+            Vec2::new(10.0, 10.0),
+            Vec2::new(20.0, 20.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(300.0, 100.0),
+        );
+        _assert_near(
+            transform_point(fit, Vec2::new(10.0, 10.0)),
+            Vec2::new(100.0, 0.0),
+        );
+        _assert_near(
+            transform_point(fit, Vec2::new(20.0, 20.0)),
+            Vec2::new(200.0, 100.0),
+        );
+        // and the other way around is as wide as the square
+        let fit = fit_rectangle(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(300.0, 100.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(20.0, 20.0),
+        );
+        _assert_near(
+            transform_point(fit, Vec2::new(0.0, 0.0)),
+            Vec2::new(10.0, 15.0 - 5.0 / 3.0),
+        );
+        _assert_near(
+            transform_point(fit, Vec2::new(300.0, 100.0)),
+            Vec2::new(20.0, 15.0 + 5.0 / 3.0),
+        );
+    }
 }
@@ -0,0 +1,278 @@
+//! Uncompressed video streams that encoders read directly, frame by frame.
+//!
+//! [`VideoFormat::Y4m`] is a YUV4MPEG2 stream which carries its size and frame rate, so
+//! `ffmpeg -i zoom.y4m zoom.mp4` is all it takes. The raw formats are bare frames one after
+//! another, for example for `ffmpeg -f rawvideo -pix_fmt yuv420p -s 1920x1080 -r 30 -i -`.
+
+use std::{fmt, io, str::FromStr};
+
+use crate::image::Image;
+
+/// The order of the channels in the 4 bytes of a pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelOrder {
+    /// `Rgba8Unorm`, which is what headless rendering produces
+    #[default]
+    Rgba,
+    /// `Bgra8Unorm`, which is what many window surfaces use
+    Bgra,
+}
+
+/// A frame as a full resolution luma plane and two chroma planes at half the resolution in both
+/// directions, rounded up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Yuv420 {
+    pub width: u32,
+    pub height: u32,
+    pub y: Vec<u8>,
+    pub u: Vec<u8>,
+    pub v: Vec<u8>,
+}
+
+impl Yuv420 {
+    /// Convert 8 bit pixels to limited range BT.601, which is what players assume for video
+    /// without color information. Chroma is the average of every 2 by 2 block of pixels.
+    pub fn from_pixels(pixels: &[u8], width: u32, height: u32, order: ChannelOrder) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        assert_eq!(pixels.len(), width * height * 4, "wrong number of pixels");
+        let rgb = |x: usize, y: usize| {
+            let pixel = &pixels[(y * width + x) * 4..][..4];
+            let [r, g, b] = match order {
+                ChannelOrder::Rgba => [pixel[0], pixel[1], pixel[2]],
+                ChannelOrder::Bgra => [pixel[2], pixel[1], pixel[0]],
+            };
+            [r, g, b].map(|it| it as f32 / 255.0)
+        };
+        let to_u8 = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+
+        let mut y_plane = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let [r, g, b] = rgb(x, y);
+                y_plane.push(to_u8(16.0 + 65.481 * r + 128.553 * g + 24.966 * b));
+            }
+        }
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+        let mut u_plane = Vec::with_capacity(chroma_width * chroma_height);
+        let mut v_plane = Vec::with_capacity(chroma_width * chroma_height);
+        for cy in 0..chroma_height {
+            for cx in 0..chroma_width {
+                let mut sum = [0.0; 3];
+                let mut count = 0.0;
+                for y in 2 * cy..(2 * cy + 2).min(height) {
+                    for x in 2 * cx..(2 * cx + 2).min(width) {
+                        let color = rgb(x, y);
+                        (0..3).for_each(|i| sum[i] += color[i]);
+                        count += 1.0;
+                    }
+                }
+                let [r, g, b] = sum.map(|it| it / count);
+                u_plane.push(to_u8(128.0 - 37.797 * r - 74.203 * g + 112.0 * b));
+                v_plane.push(to_u8(128.0 + 112.0 * r - 93.786 * g - 18.214 * b));
+            }
+        }
+        Self {
+            width: width as u32,
+            height: height as u32,
+            y: y_plane,
+            u: u_plane,
+            v: v_plane,
+        }
+    }
+
+    pub fn from_image(image: &Image) -> Self {
+        Self::from_pixels(&image.pixels, image.width, image.height, ChannelOrder::Rgba)
+    }
+
+    /// The planes one after another, which is the `yuv420p` pixel format.
+    pub fn write(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writer.write_all(&self.y)?;
+        writer.write_all(&self.u)?;
+        writer.write_all(&self.v)
+    }
+}
+
+/// What a [`VideoWriter`] writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// A YUV4MPEG2 stream with 4:2:0 chroma
+    #[default]
+    Y4m,
+    /// Bare `yuv420p` frames
+    Yuv420,
+    /// Bare `rgba` frames as they were rendered
+    Rgba,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseVideoFormatError(String);
+
+impl fmt::Display for ParseVideoFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown video format `{}`, use y4m, yuv420 or rgba",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseVideoFormatError {}
+
+impl FromStr for VideoFormat {
+    type Err = ParseVideoFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "y4m" => Ok(VideoFormat::Y4m),
+            "yuv420" | "yuv420p" => Ok(VideoFormat::Yuv420),
+            "rgba" => Ok(VideoFormat::Rgba),
+            _ => Err(ParseVideoFormatError(s.to_string())),
+        }
+    }
+}
+
+/// Writes frames of the same size to a stream as they are rendered.
+pub struct VideoWriter<W: io::Write> {
+    writer: W,
+    format: VideoFormat,
+    width: u32,
+    height: u32,
+}
+
+impl<W: io::Write> VideoWriter<W> {
+    /// Start a stream of `width` by `height` frames, writing the header if the format has one.
+    pub fn new(
+        mut writer: W,
+        format: VideoFormat,
+        width: u32,
+        height: u32,
+        frame_rate: f64,
+    ) -> io::Result<Self> {
+        if format == VideoFormat::Y4m {
+            let (numerator, denominator) = frame_rate_ratio(frame_rate);
+            writeln!(
+                writer,
+                "YUV4MPEG2 W{width} H{height} F{numerator}:{denominator} Ip A1:1 C420jpeg"
+            )?;
+        }
+        Ok(Self {
+            writer,
+            format,
+            width,
+            height,
+        })
+    }
+
+    /// Append `image`, which has to be as large as the frames of the stream.
+    pub fn write_frame(&mut self, image: &Image) -> io::Result<()> {
+        if (image.width, image.height) != (self.width, self.height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{}x{} frame in a {}x{} video",
+                    image.width, image.height, self.width, self.height
+                ),
+            ));
+        }
+        match self.format {
+            VideoFormat::Y4m => {
+                self.writer.write_all(b"FRAME\n")?;
+                Yuv420::from_image(image).write(&mut self.writer)
+            }
+            VideoFormat::Yuv420 => Yuv420::from_image(image).write(&mut self.writer),
+            VideoFormat::Rgba => self.writer.write_all(&image.pixels),
+        }
+    }
+
+    /// Flush the stream and hand back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// `frame_rate` as a ratio of whole numbers, exact for whole numbers and to a thousandth of a
+/// frame otherwise.
+fn frame_rate_ratio(frame_rate: f64) -> (u64, u64) {
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+    let numerator = (frame_rate * 1000.0).round().max(1.0) as u64;
+    let divisor = gcd(numerator, 1000);
+    (numerator / divisor, 1000 / divisor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> Image {
+        Image {
+            width,
+            height,
+            pixels: color.repeat((width * height) as usize),
+        }
+    }
+
+    #[test]
+    fn test_convert_colors() {
+        let yuv = |color| {
+            let frame = Yuv420::from_image(&solid(2, 2, color));
+            (frame.y[0], frame.u[0], frame.v[0])
+        };
+        assert_eq!(yuv([0, 0, 0, 255]), (16, 128, 128));
+        assert_eq!(yuv([255, 255, 255, 255]), (235, 128, 128));
+        assert_eq!(yuv([255, 0, 0, 255]), (81, 90, 240));
+        assert_eq!(yuv([0, 0, 255, 255]), (41, 240, 110));
+
+        let bgra = Yuv420::from_pixels(&[0, 0, 255, 255], 1, 1, ChannelOrder::Bgra);
+        assert_eq!(bgra, Yuv420::from_image(&solid(1, 1, [255, 0, 0, 255])));
+    }
+
+    #[test]
+    fn test_chroma_of_odd_sizes() {
+        // a white column next to two black ones, the last row and column have blocks to themselves
+        let mut image = solid(3, 3, [0, 0, 0, 255]);
+        for y in 0..3 {
+            image.pixels[y * 12..][..4].copy_from_slice(&[255; 4]);
+        }
+        let frame = Yuv420::from_image(&image);
+        assert_eq!((frame.y.len(), frame.u.len(), frame.v.len()), (9, 4, 4));
+        assert_eq!(frame.y[..3], [235, 16, 16]);
+        // every block averages to a gray, which has no chroma
+        assert_eq!(frame.u, [128; 4]);
+        assert_eq!(frame.v, [128; 4]);
+    }
+
+    #[test]
+    fn test_y4m_stream() {
+        let mut video = VideoWriter::new(Vec::new(), VideoFormat::Y4m, 4, 2, 29.97).unwrap();
+        for _ in 0..2 {
+            video.write_frame(&solid(4, 2, [255; 4])).unwrap();
+        }
+        assert!(video.write_frame(&solid(2, 2, [255; 4])).is_err());
+        let stream = video.finish().unwrap();
+        let header = b"YUV4MPEG2 W4 H2 F2997:100 Ip A1:1 C420jpeg\n";
+        assert_eq!(&stream[..header.len()], header);
+        let frame = [b"FRAME\n".as_slice(), &[235; 8], &[128; 4]].concat();
+        assert_eq!(&stream[header.len()..], [frame.clone(), frame].concat());
+    }
+
+    #[test]
+    fn test_raw_streams() {
+        let image = solid(2, 2, [1, 2, 3, 4]);
+        let mut video = VideoWriter::new(Vec::new(), VideoFormat::Rgba, 2, 2, 30.0).unwrap();
+        video.write_frame(&image).unwrap();
+        assert_eq!(video.finish().unwrap(), image.pixels);
+
+        let mut video = VideoWriter::new(Vec::new(), VideoFormat::Yuv420, 2, 2, 30.0).unwrap();
+        video.write_frame(&image).unwrap();
+        assert_eq!(video.finish().unwrap().len(), 6);
+        assert_eq!(frame_rate_ratio(30.0), (30, 1));
+    }
+}
@@ -0,0 +1,135 @@
+use kurbo::{Affine, Vec2};
+
+use crate::transforms::pixel_to_complex;
+
+/// A location in the Mandelbrot set that doesn't depend on the size of the image it is shown in.
+///
+/// The window keeps track of what is shown as an [`Affine`] that moved the initial image around,
+/// which only means something together with the size of the window. A `View` can be rendered at
+/// any size and shows the same region of the set, as large as it fits, which is what is needed to
+/// render a location without a window or to move it between windows of different sizes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    /// The point of the complex plane at the center of the image
+    pub center: Vec2,
+    /// Magnification relative to the view that shows the whole set
+    pub zoom: f64,
+    /// Rotation of the image in radians, in the same direction as [`Affine::rotate`] turns points
+    /// in window coordinates
+    pub rotation: f64,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            center: Vec2::new(-0.5, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl View {
+    pub fn new(center: Vec2, zoom: f64) -> Self {
+        Self {
+            center,
+            zoom,
+            rotation: 0.0,
+        }
+    }
+
+    /// The size of a pixel in the complex plane at zoom 1, which fits the real axis from -2 to 1
+    /// and the imaginary axis from -1 to 1 into the viewport.
+    fn base_pixel_size(viewport: Vec2) -> f64 {
+        (3.0 / viewport.x).max(2.0 / viewport.y)
+    }
+
+    /// The transform from pixels of a `viewport` sized image to the complex plane.
+    pub fn pixel_to_complex(&self, viewport: Vec2) -> Affine {
+        Affine::translate(self.center)
+            * Affine::rotate(-self.rotation)
+            * Affine::scale(Self::base_pixel_size(viewport) / self.zoom)
+            * Affine::translate(-viewport / 2.0)
+    }
+
+    /// The window transform, as used by [`pixel_to_complex`], that shows this view in a window
+    /// of size `viewport`.
+    pub fn to_transform(&self, viewport: Vec2) -> Affine {
+        self.pixel_to_complex(viewport).inverse() * pixel_to_complex(viewport, Affine::IDENTITY)
+    }
+
+    /// The view shown by a window of size `viewport` that moved its image by `transform`.
+    pub fn from_transform(transform: Affine, viewport: Vec2) -> Self {
+        let to_complex = pixel_to_complex(viewport, transform);
+        let [a, b, ..] = to_complex.as_coeffs();
+        let pixel_size = to_complex.determinant().abs().sqrt();
+        Self {
+            center: (to_complex * (viewport / 2.0).to_point()).to_vec2(),
+            zoom: Self::base_pixel_size(viewport) / pixel_size,
+            rotation: (-b).atan2(a),
+        }
+    }
+
+    /// The complex point shown at `pixel` of a `viewport` sized image.
+    pub fn complex_at(&self, pixel: Vec2, viewport: Vec2) -> Vec2 {
+        (self.pixel_to_complex(viewport) * pixel.to_point()).to_vec2()
+    }
+
+    /// The view a fraction `t` of the way to `other`.
+    ///
+    /// The zoom changes at a constant rate in log space and the center moves so that the point
+    /// both views zoom about stays in place, which looks like a single smooth zoom rather than a
+    /// pan on top of one.
+    pub fn lerp(&self, other: &View, t: f64) -> View {
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        let zoom = lerp(self.zoom.ln(), other.zoom.ln()).exp();
+        let center = if (other.zoom / self.zoom).ln().abs() < 1e-9 {
+            self.center.lerp(other.center, t)
+        } else {
+            let weight = (1.0 - self.zoom / zoom) / (1.0 - self.zoom / other.zoom);
+            self.center.lerp(other.center, weight)
+        };
+        View {
+            center,
+            zoom,
+            rotation: lerp(self.rotation, other.rotation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_views_near(v0: View, v1: View) {
+        assert!(
+            (v0.center - v1.center).hypot() < 1e-9
+                && (v0.zoom / v1.zoom - 1.0).abs() < 1e-9
+                && (v0.rotation - v1.rotation).abs() < 1e-9,
+            "{v0:?} != {v1:?}"
+        );
+    }
+
+    #[test]
+    fn test_transform_round_trip() {
+        let viewport = Vec2::new(800.0, 600.0);
+        let view = View {
+            center: Vec2::new(-0.75, 0.1),
+            zoom: 40.0,
+            rotation: 0.3,
+        };
+        let transform = view.to_transform(viewport);
+        assert_views_near(View::from_transform(transform, viewport), view);
+    }
+
+    #[test]
+    fn test_default_view_fits_set() {
+        let viewport = Vec2::new(300.0, 200.0);
+        let view = View::default();
+        let top_left = view.complex_at(Vec2::ZERO, viewport);
+        let bottom_right = view.complex_at(viewport, viewport);
+        assert!((top_left - Vec2::new(-2.0, -1.0)).hypot() < 1e-12);
+        assert!((bottom_right - Vec2::new(1.0, 1.0)).hypot() < 1e-12);
+        assert_views_near(View::from_transform(Affine::IDENTITY, viewport), view);
+    }
+}
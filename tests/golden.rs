@@ -0,0 +1,178 @@
+//! Renders a set of well known views and compares them against the reference images in
+//! `tests/golden`.
+//!
+//! Images are compared perceptually: a pixel only counts as different when its color is visibly
+//! off from all pixels around it in the reference, and a few such pixels are allowed since the GPU
+//! may round differently along the edges of the set. When a comparison fails the rendered image
+//! and a diff are written to `target/tmp/golden` so the failure can be inspected.
+//!
+//! Run with `UPDATE_GOLDEN=1` to render new references with the CPU renderer.
+
+use std::path::PathBuf;
+
+use kurbo::Vec2;
+use wgpu_mandelbrot::{
+    cpu, headless::HeadlessRenderer, image::Image, settings::Settings, view::View,
+};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+/// Smallest perceptual difference between two colors that counts a pixel as different, on a
+/// scale from 0 for equal colors to 1 for black and white
+const COLOR_THRESHOLD: f64 = 0.1;
+/// Fraction of pixels that may be different before an image fails. Noisy regions like seahorse
+/// valley differ by a percent or two between GPU and CPU, while actual regressions in iteration or
+/// coloring change most of the image.
+const MAX_DIFFERENT: f64 = 0.03;
+
+fn views() -> [(&'static str, View); 4] {
+    [
+        ("full_set", View::default()),
+        (
+            "seahorse_valley",
+            View::new(Vec2::new(-0.7453, 0.1127), 100.0),
+        ),
+        (
+            "elephant_valley",
+            View::new(Vec2::new(0.285, 0.0135), 100.0),
+        ),
+        // the period 8 minibrot at -1.896918 on the real axis, where a pixel is only about one
+        // and a half f32 steps wide
+        ("deep_minibrot", View::new(Vec2::new(-1.8969227, 0.0), 1e5)),
+    ]
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"))
+}
+
+fn output_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("golden")
+        .join(format!("{name}.png"))
+}
+
+/// Perceptual difference between two colors, from 0 to 1.
+///
+/// This measures the distance in YIQ space, weighted for how sensitive the eye is to each axis,
+/// like [pixelmatch](https://github.com/mapbox/pixelmatch) does.
+fn color_difference(a: [u8; 4], b: [u8; 4]) -> f64 {
+    let yiq = |[r, g, b, _]: [u8; 4]| {
+        let [r, g, b] = [r, g, b].map(f64::from);
+        [
+            r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+            r * 0.59597799 - g * 0.27417610 - b * 0.32180189,
+            r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+        ]
+    };
+    let ([y0, i0, q0], [y1, i1, q1]) = (yiq(a), yiq(b));
+    let delta = 0.5053 * (y0 - y1).powi(2) + 0.299 * (i0 - i1).powi(2) + 0.1957 * (q0 - q1).powi(2);
+    // the difference between black and white
+    const MAX_DELTA: f64 = 35215.0;
+    (delta / MAX_DELTA).sqrt()
+}
+
+/// Compare `actual` to `expected`, returning the fraction of different pixels and an image that
+/// marks them in red over a faded copy of `expected`.
+///
+/// Iteration counts along the boundary of the set are chaotic enough that the smallest rounding
+/// difference moves detail around by a pixel, so a pixel only counts as different if none of the
+/// expected pixels around it have a similar color.
+fn compare(actual: &Image, expected: &Image) -> (f64, Image) {
+    assert_eq!(
+        (actual.width, actual.height),
+        (expected.width, expected.height),
+        "image sizes differ"
+    );
+    let (width, height) = (actual.width, actual.height);
+    let mut diff = Image::new(width, height);
+    let mut different = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let a = actual.pixel(x, y);
+            let matches = (y.saturating_sub(1)..(y + 2).min(height)).any(|ny| {
+                (x.saturating_sub(1)..(x + 2).min(width))
+                    .any(|nx| color_difference(a, expected.pixel(nx, ny)) <= COLOR_THRESHOLD)
+            });
+            let start = (y as usize * width as usize + x as usize) * 4;
+            let d = &mut diff.pixels[start..start + 4];
+            if matches {
+                let e = expected.pixel(x, y);
+                let gray = (e[0] as u32 + e[1] as u32 + e[2] as u32) / 3;
+                let faded = (255 - (255 - gray) / 4) as u8;
+                d.copy_from_slice(&[faded, faded, faded, 255]);
+            } else {
+                different += 1;
+                d.copy_from_slice(&[255, 0, 0, 255]);
+            }
+        }
+    }
+    (different as f64 / (width * height) as f64, diff)
+}
+
+/// Check every view rendered by `render` against its reference, writing the image and a diff for
+/// each one that doesn't match.
+fn check_views(renderer: &str, render: impl Fn(&View) -> Image) {
+    let mut failures = Vec::new();
+    for (name, view) in views() {
+        let actual = render(&view);
+        let reference = reference_path(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() && renderer == "cpu" {
+            actual.save_png(&reference, None).unwrap();
+            continue;
+        }
+        let expected = Image::load_png(&reference)
+            .unwrap_or_else(|err| panic!("failed to load {}: {err}", reference.display()));
+        let (different, diff) = compare(&actual, &expected);
+        if different > MAX_DIFFERENT {
+            let actual_path = output_path(&format!("{name}-{renderer}"));
+            let diff_path = output_path(&format!("{name}-{renderer}-diff"));
+            std::fs::create_dir_all(actual_path.parent().unwrap()).unwrap();
+            actual.save_png(&actual_path, None).unwrap();
+            diff.save_png(&diff_path, None).unwrap();
+            failures.push(format!(
+                "{name}: {:.2}% of pixels differ, see {} and {}",
+                different * 100.0,
+                actual_path.display(),
+                diff_path.display()
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn cpu_matches_golden_images() {
+    check_views("cpu", |view| {
+        cpu::render(view, &Settings::default(), WIDTH, HEIGHT)
+    });
+}
+
+#[test]
+fn gpu_matches_golden_images() {
+    let Ok(renderer) = pollster::block_on(HeadlessRenderer::new()) else {
+        eprintln!("skipping, no adapter available");
+        return;
+    };
+    check_views("gpu", |view| {
+        renderer
+            .render(view, &Settings::default(), WIDTH, HEIGHT)
+            .unwrap()
+    });
+}
+
+#[test]
+fn comparison_tolerates_small_differences() {
+    let expected = cpu::render(&View::default(), &Settings::default(), 40, 30);
+    let mut actual = expected.clone();
+    for channel in actual.pixels.iter_mut() {
+        *channel = channel.saturating_sub(3);
+    }
+    assert_eq!(compare(&actual, &expected).0, 0.0);
+    // a white pixel in the blue inside of the set
+    let start = (15 * 40 + 20) * 4;
+    actual.pixels[start..start + 4].copy_from_slice(&[255, 255, 255, 255]);
+    assert_eq!(compare(&actual, &expected).0, 1.0 / (40.0 * 30.0));
+}